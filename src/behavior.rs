@@ -4,56 +4,64 @@ use crate::prelude::*;
 use crate::combat::*;
 use macroquad::rand::*;
 
-pub fn act(world: &mut World, resources: &mut Resources, actor: Entity,behavior: Behavior) {
+//returns the energy the actor spent
+pub fn act(world: &mut World, resources: &mut Resources, actor: Entity,behavior: Behavior) -> i32 {
     let player = player(world).unwrap();
 
-    let node = match behavior {
+    //a blind monster can't find the player, a confused one only sometimes
+    let lost = has_status(world,actor,StatusKind::Blindness) ||
+        (has_status(world,actor,StatusKind::Confusion) && gen_range(0,2) == 0);
+    let node = if lost {
+        BehaviorNode::Wander
+    } else {
+        match behavior {
         Behavior::Erratic(percent) => BehaviorNode::Erratic(player,percent),
         Behavior::Slow(percent) => BehaviorNode::Slow(player,percent),
         Behavior::ApproachAndAttack => BehaviorNode::ApproachAndAttackOrWander(player),
-
+        }
     };
     let behavior_result = node.build(actor).tick(world,resources);
 
     match behavior_result {
-        BehaviorResult::Fail => {
-            dbg!();
-
-        },
-        BehaviorResult::Success => {
-            dbg!();
-
-        },
+        BehaviorResult::Fail => ACTION_COST,
+        BehaviorResult::Success => ACTION_COST,
         BehaviorResult::Acting(action) => {
             match action {
                 ActorAction::Wait => {
+                    ACTION_COST
                 }
                 ActorAction::Move{origin:_,destination} => {
-                    if let Ok(mut pos) = world.get_mut::<Pos>(actor) {
-                        *pos = destination.into();
-                    }
+                    set_pos(world,&mut resources.spatial_index,actor,destination);
+                    ACTION_COST
                 }
                 ActorAction::Attack{target,weapon} => {
+                    let cost = weapon.attack.cost;
                     if let Some(attack_data) = get_attack(world,actor) {
-                        attack(world,actor,target,weapon.attack)
+                        if weapon.attack.fireball {
+                            if let Some(pos) = get_cloned::<Pos>(world,target) {
+                                fireball(world,resources,actor,pos.into(),weapon.attack);
+                            }
+                        } else {
+                            attack(world,resources,actor,target,weapon.attack)
+                        }
                     }
+                    cost
                 }
                 ActorAction::Wander => {
                     use macroquad::rand::*;
-                    let block_map = stepping_tiles(world);
-                    if let Ok(mut pos) = world.get_mut::<Pos>(actor) {
+                    let diagonals = resources.settings.diagonals;
+                    if let Some(pos) = get_cloned::<Pos>(world,actor) {
                         let successors: Vec<(i32,i32)>
-                            = vec![(pos.x-1,pos.y),(pos.x+1,pos.y),
-                                (pos.x,pos.y-1),(pos.x,pos.y+1)]
+                            = neighbours((pos.x,pos.y),diagonals)
                             .into_iter()
-                            .filter(|p|{!block_map.contains(&p)})
+                            .filter(|p|{!is_blocked(world,resources,*p)})
                             .collect();
                         
                         if let Some(dest) = successors.choose() {
-                            pos.x = dest.0;
-                            pos.y = dest.1;
+                            set_pos(world,&mut resources.spatial_index,actor,*dest);
                         }
                     }
+                    ACTION_COST
                 }
             }
         }
@@ -148,9 +156,9 @@ impl BehaviorNode {
                 })
             }
             BehaviorNode::InFOV => {
-                action(move |world: &World,_resources: &Resources| {
-                    if let Ok(appearance) = world.get::<Appearance>(actor) {
-                        if appearance.in_fov {
+                action(move |world: &World,resources: &Resources| {
+                    if let Ok(player) = player(world) {
+                        if can_see(world,resources,actor,player) {
                             return BehaviorResult::Success;
                         }
                     }
@@ -159,9 +167,9 @@ impl BehaviorNode {
             }
             BehaviorNode::InAttackRange(target) => {
                 let target = target.clone();
-                action(move |world: &World,_resources: &Resources| {
+                action(move |world: &World,resources: &Resources| {
                     if let Some(weapon) = get_attack(world,actor) {
-                        if let Some(true) = weapon.range.in_range(world,actor,target) {
+                        if let Some(true) = weapon.range.in_range(world,actor,target,resources.settings.diagonals) {
                             return BehaviorResult::Success;
                         }
                     }
@@ -191,9 +199,9 @@ impl BehaviorNode {
             },
             BehaviorNode::ApproachEntity(target) => {
                 let target = target.clone();
-                action(move |world: &World, _resources: &Resources|{
+                action(move |world: &World, resources: &Resources|{
                     use crate::movement::my_pathfinding::basic_path;
-                    if let Some((path,distance)) = basic_path(world,actor,target) {
+                    if let Some((path,distance)) = basic_path(world,resources,actor,target,resources.settings.diagonals) {
                         if distance == 1 {
                             return BehaviorResult::Success;
                         } else {
@@ -0,0 +1,154 @@
+use macroquad::rand::*;
+use crate::prelude::*;
+use crate::dungeon_gen::{DungeonMap,MapGenerator,Room};
+
+//areas aren't split below this, so every leaf has space for a room and the
+//walls around it
+const MIN_LEAF: usize = 8;
+const MIN_ROOM: usize = 4;
+//percent chance a room gets a vault stamped into it if one fits
+const VAULT_CHANCE: i32 = 40;
+
+//rooms and corridors, the map is split in two over and over and every
+//split is joined back up with a hallway
+pub struct BspMap {
+    pub width: usize,
+    pub height: usize,
+    pub level: u32,
+    pub seed: u64,
+}
+
+//a room and the part of it taken up by a vault, if it has one
+struct BspRoom {
+    room: Room,
+    vault: Option<((usize,usize),(usize,usize))>,
+}
+
+impl BspRoom {
+    //somewhere a hallway can end, on open floor outside of any vault so
+    //the stairs can't end up blocking it
+    fn hallway_end(&self, map: &DungeonMap) -> (usize,usize) {
+        let tiles: Vec<(usize,usize)> = self.room.tiles_within().into_iter()
+            .filter(|tile|{
+                map.get_index(*tile)
+                    .map(|index|map.terrain[index] == EntityKind::Floor)
+                    .unwrap_or(false)
+            })
+            .filter(|tile|match self.vault {
+                Some((pos,size)) => {
+                    tile.0 < pos.0 || tile.1 < pos.1 ||
+                        tile.0 >= pos.0+size.0 || tile.1 >= pos.1+size.1
+                }
+                None => true,
+            })
+            .collect();
+        tiles.choose().or(tiles.last()).copied().unwrap_or(self.room.pos)
+    }
+}
+
+//a random number in low..high, gen_range can land on high itself which
+//would be a room or tile past the end
+fn roll(low: usize, high: usize) -> usize {
+    let n = gen_range(low,high);
+    if n < high {n} else {low}
+}
+
+impl BspMap {
+    //splits the area until it's too small to split, with a room in every
+    //leaf, and joins each pair of halves with a hallway between them
+    fn split(&self, pos: (usize,usize), size: (usize,usize), rooms: &mut Vec<BspRoom>, hallways: &mut Vec<(usize,usize)>) {
+        let can_split_x = size.0 >= MIN_LEAF*2;
+        let can_split_y = size.1 >= MIN_LEAF*2;
+        let split_x = match (can_split_x,can_split_y) {
+            (false,false) => {
+                rooms.push(self.leaf_room(pos,size));
+                return;
+            }
+            (true,false) => true,
+            (false,true) => false,
+            //long areas are cut across so rooms don't end up as slivers
+            (true,true) if size.0 > size.1*5/4 => true,
+            (true,true) if size.1 > size.0*5/4 => false,
+            (true,true) => gen_range(0,2) == 0,
+        };
+
+        let first = rooms.len();
+        if split_x {
+            let cut = roll(MIN_LEAF,size.0-MIN_LEAF+1);
+            self.split(pos,(cut,size.1),rooms,hallways);
+            let second = rooms.len();
+            self.split((pos.0+cut,pos.1),(size.0-cut,size.1),rooms,hallways);
+            hallways.push((roll(first,second),roll(second,rooms.len())));
+        } else {
+            let cut = roll(MIN_LEAF,size.1-MIN_LEAF+1);
+            self.split(pos,(size.0,cut),rooms,hallways);
+            let second = rooms.len();
+            self.split((pos.0,pos.1+cut),(size.0,size.1-cut),rooms,hallways);
+            hallways.push((roll(first,second),roll(second,rooms.len())));
+        }
+    }
+
+    //a room somewhere in the leaf with at least a wall between it and the
+    //leaf's edge
+    fn leaf_room(&self, pos: (usize,usize), size: (usize,usize)) -> BspRoom {
+        let space = (size.0-2,size.1-2);
+        let w = roll(MIN_ROOM,space.0+1);
+        let h = roll(MIN_ROOM,space.1+1);
+        let x = pos.0+1+roll(0,space.0-w+1);
+        let y = pos.1+1+roll(0,space.1-h+1);
+        BspRoom {
+            room: Room {
+                pos: (x,y),
+                size: (w,h),
+                danger: false,
+                loot: false,
+                player: false,
+                exit: false,
+            },
+            vault: None,
+        }
+    }
+}
+
+impl MapGenerator for BspMap {
+    fn build(&self) -> DungeonMap {
+        srand(self.seed);
+        let mut map = DungeonMap::new(self.width,self.height,EntityKind::Wall);
+
+        let mut rooms: Vec<BspRoom> = Vec::new();
+        let mut hallways: Vec<(usize,usize)> = Vec::new();
+        self.split((0,0),(self.width,self.height),&mut rooms,&mut hallways);
+
+        //the player starts at one end of the floor and the way up is at the other
+        let last = rooms.len()-1;
+        let (enter,exit) = if gen_range(0,2) == 0 {(0,last)} else {(last,0)};
+        rooms[enter].room.player = true;
+        rooms[exit].room.exit = true;
+        let loot = roll(0,rooms.len());
+        rooms[loot].room.loot = true;
+
+        for bsp_room in rooms.iter_mut() {
+            let room = &bsp_room.room;
+            map.fill_rect(EntityKind::Floor,room.pos,room.size).ok();
+            //vaults keep a tile of open floor around them so the room can
+            //still be walked through
+            if !room.player && !room.exit && gen_range(0,100) < VAULT_CHANCE {
+                if let Some(vault) = choose_vault(room.size.0-2,room.size.1-2) {
+                    let x = room.pos.0+1+roll(0,room.size.0-2-vault.width+1);
+                    let y = room.pos.1+1+roll(0,room.size.1-2-vault.height+1);
+                    map.stamp_vault(&vault,(x,y),self.level);
+                    bsp_room.vault = Some(((x,y),(vault.width,vault.height)));
+                }
+            }
+            map.furnish_room(&bsp_room.room,self.level);
+        }
+
+        for (a,b) in hallways {
+            let start = rooms[a].hallway_end(&map);
+            let finish = rooms[b].hallway_end(&map);
+            map.dig_hallway(start,finish,[&rooms[a].room,&rooms[b].room]);
+        }
+
+        map
+    }
+}
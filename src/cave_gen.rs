@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+use macroquad::rand::*;
+use pathfinding::prelude::{bfs_reach,dijkstra_all};
+use crate::prelude::*;
+use crate::dungeon_gen::{DungeonMap,MapGenerator,exit_kind};
+
+//percent of the tiles that start out as wall before smoothing
+const WALL_PERCENT: i32 = 45;
+const SMOOTHING_STEPS: u32 = 5;
+//a tile becomes wall when at least this many of the 9 tiles around it,
+//itself included, are walls
+const WALLS_TO_FILL: usize = 5;
+//open tiles for every monster
+const TILES_PER_ENEMY: usize = 40;
+//the biggest cave has to take up at least 1/this of the map, or the
+//cave is grown again
+const MIN_CAVE_FRACTION: usize = 4;
+const MAX_ATTEMPTS: u32 = 10;
+
+//open caverns grown with a cellular automaton, only the biggest cave is
+//kept so all of it can be walked
+pub struct CaveMap {
+    pub width: usize,
+    pub height: usize,
+    pub level: u32,
+    pub seed: u64,
+}
+
+impl CaveMap {
+    fn on_edge(&self, index: usize) -> bool {
+        let (x,y) = (index%self.width,index/self.width);
+        x == 0 || y == 0 || x == self.width-1 || y == self.height-1
+    }
+
+    fn grow(&self) -> Vec<bool> {
+        let size = self.width*self.height;
+        let mut walls: Vec<bool> = (0..size)
+            .map(|i|self.on_edge(i) || gen_range(0,100) < WALL_PERCENT)
+            .collect();
+        for _ in 0..SMOOTHING_STEPS {
+            walls = (0..size)
+                .map(|i|self.on_edge(i) || self.around(i).filter(|j|walls[*j]).count()+walls[i] as usize >= WALLS_TO_FILL)
+                .collect();
+        }
+        walls
+    }
+
+    //the tiles around an index that isn't on the edge
+    fn around(&self, index: usize) -> impl Iterator<Item=usize> {
+        let width = self.width as isize;
+        [-width-1,-width,-width+1,-1,1,width-1,width,width+1].into_iter()
+            .map(move |offset|(index as isize+offset) as usize)
+    }
+
+    fn open_neighbours(&self, walls: &[bool], index: usize) -> Vec<usize> {
+        let width = self.width;
+        [index-width,index+width,index-1,index+1].into_iter()
+            .filter(|i|!walls[*i])
+            .collect()
+    }
+
+    //the open tiles of the biggest cave, in the order they were reached
+    fn biggest_cave(&self, walls: &[bool]) -> Vec<usize> {
+        let mut seen: HashSet<usize> = HashSet::new();
+        let mut biggest = Vec::new();
+        for start in 0..walls.len() {
+            if walls[start] || seen.contains(&start) {
+                continue;
+            }
+            let cave: Vec<usize> = bfs_reach(start,|i|self.open_neighbours(walls,*i)).collect();
+            seen.extend(cave.iter().copied());
+            if cave.len() > biggest.len() {
+                biggest = cave;
+            }
+        }
+        biggest
+    }
+}
+
+impl MapGenerator for CaveMap {
+    fn build(&self) -> DungeonMap {
+        srand(self.seed);
+        let mut walls = self.grow();
+        let mut cave = self.biggest_cave(&walls);
+        for _ in 1..MAX_ATTEMPTS {
+            if cave.len()*MIN_CAVE_FRACTION >= walls.len() {
+                break;
+            }
+            walls = self.grow();
+            cave = self.biggest_cave(&walls);
+        }
+
+        let mut map = DungeonMap::new(self.width,self.height,EntityKind::Wall);
+        for &index in cave.iter() {
+            map.terrain[index] = EntityKind::Floor;
+        }
+
+        //the way up goes as far from where the player starts as the cave allows
+        let start = cave.choose().or(cave.first()).copied().unwrap_or(0);
+        let distance = dijkstra_all(&start,|index|{
+            self.open_neighbours(&walls,*index).into_iter().map(|next|(next,1))
+        });
+        let exit = cave.iter().copied()
+            .max_by_key(|index|distance.get(index).map(|(_,steps)|*steps).unwrap_or(0))
+            .unwrap_or(start);
+        map.terrain[exit] = exit_kind(self.level);
+        map.player_pos = map.index_as_pos(start).ok();
+
+        let mut free: Vec<usize> = cave.iter().copied()
+            .filter(|index|*index != start && *index != exit)
+            .collect();
+        free.shuffle();
+        for _ in 0..free.len()/TILES_PER_ENEMY {
+            if let Some(enemy) = choose_enemy(self.level) {
+                if let Some(index) = free.pop() {
+                    map.actors[index].replace(enemy);
+                }
+            }
+        }
+        for _ in 0..gen_range(1,3) {
+            if let Some(item) = choose_item(self.level) {
+                if let Some(index) = free.pop() {
+                    map.items[index].push(item);
+                }
+            }
+        }
+
+        map
+    }
+}
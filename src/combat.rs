@@ -1,28 +1,32 @@
 use hecs::*;
 use macroquad::rand::*;
 use macroquad::color::*;
+use serde::{Serialize,Deserialize};
 
 use crate::prelude::*;
 
 
-#[derive(Clone,Debug)]
+#[derive(Clone,Debug,Serialize,Deserialize)]
 pub enum Range {
     Meele,
     Ranged(i32),
 }
 
 impl Range {
-    pub fn in_range(&self,world: &World,actor:Entity,target:Entity)->Option<bool> {
+    //melee reaches the diagonals too when movement does
+    pub fn in_range(&self,world: &World,actor:Entity,target:Entity,diagonals:bool)->Option<bool> {
         let actor_pos = get_cloned::<Pos>(world,actor)?;
         let target_pos = get_cloned::<Pos>(world,target)?;
         match self {
             Range::Meele => {
                 use pathfinding::prelude::absdiff;
-                Some(
-                    absdiff(actor_pos.x,target_pos.x) +
-                    absdiff(actor_pos.y,target_pos.y)
-                    ==1
-                )
+                let dx = absdiff(actor_pos.x,target_pos.x);
+                let dy = absdiff(actor_pos.y,target_pos.y);
+                if diagonals {
+                    Some(dx.max(dy) == 1)
+                } else {
+                    Some(dx+dy == 1)
+                }
             }
             Range::Ranged(range) => {
                 let (_,dist) = unblocked_attack_line(world,
@@ -33,14 +37,21 @@ impl Range {
     }
 }
 
-#[derive(Clone,Debug)]
+#[derive(Clone,Debug,Serialize,Deserialize)]
+#[serde(default,deny_unknown_fields)]
 pub struct AttackData {
     pub range: Range,
     pub damage_low: i32,
     pub damage_high: i32,
     pub to_hit: i32,
     pub axe: bool,
+    //explodes on the target tile, hitting everything within radius
     pub fireball: bool,
+    pub radius: i32,
+    //energy it takes to make the attack
+    pub cost: i32,
+    //applied to the target when the attack does damage
+    pub on_hit: Option<StatusEffect>,
 }
 
 impl Default for AttackData {
@@ -52,12 +63,16 @@ impl Default for AttackData {
             to_hit: 0,
             axe: false,
             fireball: false,
+            radius: 1,
+            cost: ACTION_COST,
+            on_hit: None,
         }
     }
 }
 
 pub fn attack(
     world: &mut World,
+    resources: &mut Resources,
     attacker: Entity,
     target:Entity,
     attack:AttackData,
@@ -65,45 +80,124 @@ pub fn attack(
     //hackish way of getting a clone or default out of this convoluted world::get method
     let defense = get_defense(world,target);
 
-    let hit: bool = {
-        let hit_roll = gen_range(0,20)+ attack.to_hit;
+    let blind = if has_status(world,attacker,StatusKind::Blindness) {BLIND_TO_HIT} else {0};
+    //there's no dodging an explosion
+    let hit: bool = attack.fireball || {
+        let hit_roll = gen_range(0,20)+ attack.to_hit + blind;
         hit_roll > defense.dodging + 10
     };
     let target_pos = get_cloned::<Pos>(world,target).unwrap();
     let attacker_pos = get_cloned::<Pos>(world,attacker).unwrap();
     let attacker_appearance = get_cloned::<Appearance>(world,attacker).unwrap();
+    let player = player(world).unwrap();
+
+    //log lines read "You hit the Bat" or "Goblin hits you"
+    let subject = capitalize(&log_name(world,attacker));
+    let object = if target == attacker {
+        "yourself".to_owned()
+    } else if target == player {
+        "you".to_owned()
+    } else {
+        format!("the {}",log_name(world,target))
+    };
+    let verb = |you: &str, they: &str| if attacker == player {you.to_owned()} else {they.to_owned()};
+    let log_color = if target == player {RED} else {WHITE};
 
     if hit {
         let armor_roll = gen_range(0,defense.armor+1);
+        let strength = if has_status(world,attacker,StatusKind::Strength) {STRENGTH_BONUS} else {0};
         let damage = (
-            gen_range(attack.damage_low,attack.damage_high+1)-
+            gen_range(attack.damage_low,attack.damage_high+1)+
+            strength-
             armor_roll
         ).max(0);
+        let mut killed = false;
         if let Ok(mut health) = world.get_mut::<Health>(target) {
             health.current -= damage;
+            killed = health.current <= 0;
         }
         
-        let player = player(world).unwrap();
         let message_color = if player == target {
             RED
         } else {
             GREEN
         };
 
-        emit_message(world,target_pos.into(),format!("{}",damage),message_color);
-        emit_ranged_attack_animation(world,attacker_pos.into(),target_pos.into(),attacker_appearance.color);
+        let line = if killed {
+            format!("{} {} {}",subject,verb("kill","kills"),object)
+        } else if damage == 0 {
+            format!("{} {} {} but {} no damage",subject,verb("hit","hits"),object,verb("do","does"))
+        } else {
+            format!("{} {} {} for {}",subject,verb("hit","hits"),object,damage)
+        };
+        log(resources,line,if killed && target != player {GREEN} else {log_color});
+
+        if let Some(effect) = attack.on_hit {
+            if damage > 0 && !killed {
+                apply_status(world,resources,target,effect);
+            }
+        }
+
+        emit_message(resources,target_pos.into(),format!("{}",damage),message_color);
+        if !attack.fireball {
+            emit_ranged_attack_animation(resources,attacker_pos.into(),target_pos.into(),attacker_appearance.color);
+        }
 
     } else {
-        let player = player(world).unwrap();
-        emit_message(world,target_pos.into(),"Miss".to_owned(),YELLOW);
-        emit_ranged_attack_animation(world,attacker_pos.into(),target_pos.into(),YELLOW);
+        log(resources,format!("{} {} {}",subject,verb("miss","misses"),object),GRAY);
+        emit_message(resources,target_pos.into(),"Miss".to_owned(),YELLOW);
+        emit_ranged_attack_animation(resources,attacker_pos.into(),target_pos.into(),YELLOW);
+    }
+
+    remove_dead_entities(world,resources);
+}
+
+//attacks everything with health caught in the blast, the attacker included
+pub fn fireball(
+    world: &mut World,
+    resources: &mut Resources,
+    attacker: Entity,
+    center: (i32,i32),
+    attack_data: AttackData,
+) {
+    let tiles = blast_tiles(world,center,attack_data.radius);
+    emit_blast_animation(resources,center,attack_data.radius,ORANGE);
+
+    let mut targets = Vec::new();
+    for (ent,(pos,_)) in world.query::<(&Pos,&Health)>().iter() {
+        if tiles.contains(&(pos.x,pos.y)) {
+            targets.push(ent);
+        }
+    }
+    for target in targets {
+        //an earlier hit can end the attacker's run
+        if world.contains(target) && world.contains(attacker) {
+            attack(world,resources,attacker,target,attack_data.clone());
+        }
     }
+}
 
-    remove_dead_entities(world);
+//tiles within radius of the center that the blast can reach without
+//passing through walls or closed doors
+pub fn blast_tiles(world: &World, center: (i32,i32), radius: i32) -> Vec<(i32,i32)> {
+    let mut tiles = Vec::new();
+    for x in center.0-radius..=center.0+radius {
+        for y in center.1-radius..=center.1+radius {
+            let (dx,dy) = (x-center.0,y-center.1);
+            if dx*dx+dy*dy > radius*radius+radius {
+                continue;
+            }
+            if (x,y) == center || unblocked_attack_line(world,center,(x,y)).is_some() {
+                tiles.push((x,y));
+            }
+        }
+    }
+    tiles
 }
 
 pub fn remove_dead_entities(
-    world: &mut World
+    world: &mut World,
+    resources: &mut Resources,
 ) {
     let player = player(world).unwrap();
     let mut entities_to_remove = Vec::new();
@@ -115,23 +209,35 @@ pub fn remove_dead_entities(
 
     entities_to_remove.iter().for_each(|ent|{
         if *ent != player {
-            world.despawn(*ent).ok();
+            //monsters drop whatever they had equipped where they fell
+            if let (Some(pos),Some(equipment)) = (get_cloned::<Pos>(world,*ent),get_cloned::<Equipment>(world,*ent)) {
+                for item in [equipment.weapon,equipment.armor].into_iter().flatten() {
+                    set_pos(world,&mut resources.spatial_index,item,pos.into());
+                }
+            }
+            despawn(world,&mut resources.spatial_index,*ent);
 
         }
     });
 }
 
-fn get_defense(world: &World, entity:Entity) -> Defense {
+pub fn get_defense(world: &World, entity:Entity) -> Defense {
     let mut base_defense = get_cloned::<Defense>(world,entity).unwrap_or_default();
 
     if let Some(equipment) = get_cloned::<Equipment>(world,entity) {
         if let Some(armor) = equipment.armor {
-            if let Some(defense) = get_cloned::<Defense>(world,armor) {
-                base_defense.dodging += defense.dodging;
-                base_defense.armor += defense.armor;
+            if let Some(armor) = get_cloned::<Armor>(world,armor) {
+                base_defense.dodging += armor.defense.dodging;
+                base_defense.armor += armor.defense.armor;
             }
         }
     }
+    if has_status(world,entity,StatusKind::Haste) {
+        base_defense.dodging += HASTE_DODGING;
+    }
+    if has_status(world,entity,StatusKind::Slow) {
+        base_defense.dodging -= HASTE_DODGING;
+    }
     base_defense
 }
 
@@ -154,6 +260,27 @@ pub fn get_attack(world: &World, entity:Entity) -> Option<AttackData> {
     None
 }
 
+//bumping into an enemy while wielding a ranged weapon falls back on the
+//attacker's natural weapon
+pub fn get_melee_attack(world: &World, entity:Entity) -> Option<AttackData> {
+    match get_attack(world,entity) {
+        Some(AttackData{range: Range::Ranged(_),..}) => {
+            get_cloned::<Weapon>(world,entity).map(|weapon|weapon.attack)
+        }
+        attack => attack,
+    }
+}
+
+//the ranged weapon the entity has equipped, if any
+pub fn get_ranged_weapon(world: &World, entity:Entity) -> Option<AttackData> {
+    let equipment = get_cloned::<Equipment>(world,entity)?;
+    let weapon = get_cloned::<Weapon>(world,equipment.weapon?)?;
+    match weapon.attack.range {
+        Range::Ranged(_) => Some(weapon.attack),
+        Range::Meele => None,
+    }
+}
+
 pub fn attack_line(a:(i32,i32),b:(i32,i32))->(Vec<(i32,i32)>,u32) {
     use bresenham::Bresenham;
     let asize = (a.0 as isize,a.1 as isize);
@@ -1,14 +1,18 @@
 use macroquad::color::*;
 use hecs::*;
+use serde::{Serialize,Deserialize};
 use crate::combat::AttackData;
 
+#[derive(Clone,Serialize,Deserialize)]
 pub struct Armor {
-    pub defense: i32,
+    pub defense: Defense,
 }
 
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
+#[serde(default,deny_unknown_fields)]
 pub struct Appearance {
     pub sprite: u32,
+    #[serde(with = "color_serde")]
     pub color: Color,
     pub layer: u32,
     pub ignore_overlap: bool,
@@ -27,17 +31,17 @@ impl Default for Appearance {
     }
 }
 
-#[derive(Copy,Clone)]
+#[derive(Copy,Clone,Serialize,Deserialize)]
 pub enum Behavior {
     ApproachAndAttack,
     Erratic(u32),
     Slow(u32),
 }
 
-#[derive(Copy,Clone)]
+#[derive(Copy,Clone,Serialize,Deserialize)]
 pub struct BlocksSight;
 
-#[derive(Copy,Clone,Debug)]
+#[derive(Copy,Clone,Debug,Serialize,Deserialize)]
 pub enum Bump {
     BlocksMovement,
     OpenDoor,
@@ -45,25 +49,26 @@ pub enum Bump {
     NextLevel
 }
 
-#[derive(Debug,Default,Clone)]
+#[derive(Debug,Default,Clone,Serialize,Deserialize)]
+#[serde(default,deny_unknown_fields)]
 pub struct Defense {
     pub dodging: i32,
     pub armor: i32,
 }
 
-#[derive(Debug,Clone)]
+#[derive(Debug,Clone,Serialize,Deserialize)]
 pub struct Equipment {
     pub weapon: Option<Entity>,
     pub armor: Option<Entity>
 }
 
-#[derive(Copy,Clone)]
+#[derive(Copy,Clone,Debug,PartialEq,Serialize,Deserialize)]
 pub enum Equipable {
     Weapon,
     Armor
 }
 
-#[derive(Copy,Clone)]
+#[derive(Copy,Clone,Serialize,Deserialize)]
 pub struct Health {
     pub max: i32,
     pub current: i32,
@@ -78,28 +83,95 @@ impl Health {
     }
 }
 
-#[derive(Copy,Clone)]
+#[derive(Copy,Clone,Serialize,Deserialize)]
 pub struct Item;
 
+//arrows, fired by whatever ranged weapon the player wields
+#[derive(Copy,Clone,Debug,Serialize,Deserialize)]
+pub struct Ammo;
 
-#[derive(Copy,Clone)]
+//how far an actor can see
+#[derive(Copy,Clone,Debug,Serialize,Deserialize)]
+pub struct Light {
+    pub radius: u32,
+}
+
+//how heavy one of the item is
+#[derive(Copy,Clone,Debug,Serialize,Deserialize)]
+pub struct Weight {
+    pub weight: u32,
+}
+
+//identical items share one entity, template is the id they were spawned from
+#[derive(Clone,Debug,Serialize,Deserialize)]
+pub struct Stack {
+    pub template: String,
+    pub count: u32,
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Serialize,Deserialize)]
+pub enum StatusKind {
+    Poison,
+    Bleeding,
+    Haste,
+    Slow,
+    Confusion,
+    Blindness,
+    Regeneration,
+    Strength,
+}
+
+//turns are counted in TICKS_PER_TURN, not in the entity's own actions
+#[derive(Copy,Clone,Debug,Serialize,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub turns: u32,
+}
+
+#[derive(Clone,Debug,Default,Serialize,Deserialize)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+//actors gain speed worth of energy every tick and can act while it is not
+//negative, each action then costs some of it
+#[derive(Copy,Clone,Debug,Serialize,Deserialize)]
+pub struct Energy {
+    pub current: i32,
+    pub speed: i32,
+}
+
+impl Energy {
+    pub fn new(speed: i32) -> Self {
+        Self {
+            current: 0,
+            speed,
+        }
+    }
+}
+
+
+#[derive(Copy,Clone,Serialize,Deserialize)]
 pub struct OnLevel;
 
+#[derive(Clone,Serialize,Deserialize)]
 pub struct MonsterMemory {
     pub time_to_remember: i32,
     pub strength: i32,
 }
 
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Name {
     pub name: String,
     pub description: String,
 }
 
-#[derive(Copy,Clone)]
+#[derive(Copy,Clone,Serialize,Deserialize)]
 pub struct PlayerMemory;
 
-#[derive(Clone)]
+#[derive(Clone,Serialize,Deserialize)]
 pub struct Player {
     //pub inventory: Vec<Entity>,
 }
@@ -112,7 +184,7 @@ impl Player {
     }
 }
 
-#[derive(Debug,Clone,Copy)]
+#[derive(Debug,Clone,Copy,Serialize,Deserialize)]
 pub struct Pos{
     pub x: i32,
     pub y: i32
@@ -141,21 +213,85 @@ impl Into<(i32,i32)> for Pos {
     }
 }
 
-#[derive(Clone,Debug)]
+#[derive(Clone,Debug,Serialize,Deserialize)]
 pub enum Useable {
     //potions
     Heal,
-    //Speed,
-    //Strength,
+    Speed,
+    Strength,
+    Regeneration,
 
     //scrolls
-    //Fireball,
+    Fireball(AttackData),
+    //to a random spot on the floor
+    Teleport,
+    //to a visible tile within range
+    Blink(i32),
     MagicMapping,
     Throw(AttackData,bool),
 
 }
 
-#[derive(Clone,Debug)]
+#[derive(Clone,Debug,Serialize,Deserialize)]
 pub struct Weapon {
     pub attack: AttackData,
 }
+
+//macroquad colors aren't serializable, so they are stored as (r,g,b,a),
+//hand written files can also use the name of a macroquad color
+pub mod color_serde {
+    use macroquad::color::*;
+    use serde::{Serialize,Serializer,Deserialize,Deserializer};
+    use serde::de::Error;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ColorDef {
+        Named(String),
+        Rgba(f32,f32,f32,f32),
+    }
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok,S::Error> {
+        (color.r,color.g,color.b,color.a).serialize(serializer)
+    }
+
+    pub fn deserialize<'de,D: Deserializer<'de>>(deserializer: D) -> Result<Color,D::Error> {
+        match ColorDef::deserialize(deserializer)? {
+            ColorDef::Rgba(r,g,b,a) => Ok(Color::new(r,g,b,a)),
+            ColorDef::Named(name) => named_color(&name)
+                .ok_or_else(||D::Error::custom(format!("unknown color `{}`",name))),
+        }
+    }
+
+    pub fn named_color(name: &str) -> Option<Color> {
+        let color = match name {
+            "LIGHTGRAY" => LIGHTGRAY,
+            "GRAY" => GRAY,
+            "DARKGRAY" => DARKGRAY,
+            "YELLOW" => YELLOW,
+            "GOLD" => GOLD,
+            "ORANGE" => ORANGE,
+            "PINK" => PINK,
+            "RED" => RED,
+            "MAROON" => MAROON,
+            "GREEN" => GREEN,
+            "LIME" => LIME,
+            "DARKGREEN" => DARKGREEN,
+            "SKYBLUE" => SKYBLUE,
+            "BLUE" => BLUE,
+            "DARKBLUE" => DARKBLUE,
+            "PURPLE" => PURPLE,
+            "VIOLET" => VIOLET,
+            "DARKPURPLE" => DARKPURPLE,
+            "BEIGE" => BEIGE,
+            "BROWN" => BROWN,
+            "DARKBROWN" => DARKBROWN,
+            "WHITE" => WHITE,
+            "BLACK" => BLACK,
+            "BLANK" => BLANK,
+            "MAGENTA" => MAGENTA,
+            _ => return None,
+        };
+        Some(color)
+    }
+}
@@ -2,13 +2,18 @@ use hecs::*;
 use macroquad::rand::*;
 use crate::prelude::*;
 use std::collections::HashSet;
+use serde::Deserialize;
+use pathfinding::prelude::{bfs_reach,dijkstra};
+use crate::cave_gen::CaveMap;
+use crate::bsp_gen::BspMap;
 
 pub struct DungeonMap {
     pub width: usize,
     pub height: usize,
     pub terrain: Vec<EntityKind>,
-    pub items: Vec<Vec<EntityKind>>,
-    pub actors: Vec<Option<EntityKind>>,
+    //items and actors are entity template ids
+    pub items: Vec<Vec<String>>,
+    pub actors: Vec<Option<String>>,
     pub player_pos: Option<(usize,usize)>,
 }
 
@@ -38,8 +43,9 @@ impl DungeonMap {
         let mut x = 0;
         let mut y = 0;
         for items in self.items.iter() {
-            for kind in items {
-                spawn_at(world,*kind,(x,y).into());
+            for id in items {
+                let ent = spawn_template(world,id);
+                world.insert_one(ent,Pos::new(x,y)).ok();
             }
             x+=1;
             if x >= self.width as i32 {
@@ -58,8 +64,9 @@ impl DungeonMap {
                 }
             }
             if !at_player {
-                if let Some(kind) = kind {
-                    spawn_at(world,*kind,(x,y).into());
+                if let Some(id) = kind {
+                    let ent = spawn_template(world,id);
+                    world.insert_one(ent,Pos::new(x,y)).ok();
                 }
             }
             x+=1;
@@ -102,6 +109,82 @@ impl DungeonMap {
         }
     }
 
+    //floor and doors can be walked through, the way up only leads off the floor
+    fn is_open(&self, index: usize) -> bool {
+        matches!(self.terrain[index],EntityKind::Floor|EntityKind::Door)
+    }
+
+    fn is_exit(&self, index: usize) -> bool {
+        matches!(self.terrain[index],EntityKind::Stairs|EntityKind::Exit)
+    }
+
+    fn on_edge(&self, index: usize) -> bool {
+        let (x,y) = (index%self.width,index/self.width);
+        x == 0 || y == 0 || x == self.width-1 || y == self.height-1
+    }
+
+    fn neighbours(&self, index: usize) -> Vec<usize> {
+        let (x,y) = (index%self.width,index/self.width);
+        let mut neighbours = Vec::new();
+        if x > 0 {neighbours.push(index-1);}
+        if x+1 < self.width {neighbours.push(index+1);}
+        if y > 0 {neighbours.push(index-self.width);}
+        if y+1 < self.height {neighbours.push(index+self.width);}
+        neighbours
+    }
+
+    //every open tile the player can walk to from where they start, without
+    //going diagonally so it holds with either movement setting
+    fn reachable(&self) -> HashSet<usize> {
+        let start = match self.player_pos.map(|pos|self.get_index(pos)) {
+            Some(Ok(index)) => index,
+            _ => return HashSet::new(),
+        };
+        bfs_reach(start,|index|{
+            self.neighbours(*index).into_iter()
+                .filter(|next|self.is_open(*next))
+                .collect::<Vec<usize>>()
+        }).collect()
+    }
+
+    //the way up and any loot the player can't get to, the floor can be
+    //finished when this is empty
+    pub fn unreachable(&self) -> Vec<(usize,usize)> {
+        let reachable = self.reachable();
+        (0..self.terrain.len())
+            .filter(|index|self.is_exit(*index) || !self.items[*index].is_empty())
+            .filter(|index|{
+                !reachable.contains(index) &&
+                    !self.neighbours(*index).iter().any(|next|reachable.contains(next))
+            })
+            .filter_map(|index|self.index_as_pos(index).ok())
+            .collect()
+    }
+
+    //digs from a tile to the closest place the player can already reach,
+    //through as little wall as possible and never through the way up
+    pub fn carve_to(&mut self, tile: (usize,usize)) {
+        let reachable = self.reachable();
+        let goal = match self.get_index(tile) {
+            Ok(index) => index,
+            Err(_) => return,
+        };
+        let path = dijkstra(&goal,|index|{
+            self.neighbours(*index).into_iter()
+                .filter(|next|!self.on_edge(*next) && !self.is_exit(*next))
+                .map(|next|(next,if self.is_open(next) {1} else {CARVE_COST}))
+                .collect::<Vec<(usize,u32)>>()
+        },|index|reachable.contains(index));
+
+        if let Some((path,_)) = path {
+            for index in path {
+                if index != goal && !self.is_open(index) {
+                    self.terrain[index] = EntityKind::Floor;
+                }
+            }
+        }
+    }
+
     pub fn fill_rect(&mut self, kind: EntityKind, pos:(usize,usize), size:(usize,usize)) -> Result<(),String> {
         for index in self.rect(pos,size)? {
             self.terrain[index] = kind;
@@ -128,24 +211,121 @@ impl DungeonMap {
         self.fill_rect(EntityKind::Floor,inner_pos,inner_size)?;
         
         let actors = vec![
-            EntityKind::Player,
-            EntityKind::Rat,
+            EntityKind::Player.template_id().to_owned(),
+            EntityKind::Rat.template_id().to_owned(),
         ];
         self.fill_room_with_actors(inner_pos,inner_size,actors)?;
         self.fill_room_with_items(inner_pos,inner_size,vec![
-            EntityKind::Sword,
-            EntityKind::Sword,
-            EntityKind::Sword,
+            EntityKind::Sword.template_id().to_owned(),
+            EntityKind::Sword.template_id().to_owned(),
+            EntityKind::Sword.template_id().to_owned(),
         ])?;
 
         Ok(())
     }
 
+    //puts the player, the way up, loot and monsters on the room's open
+    //floor as the room asks for
+    pub fn furnish_room(&mut self, room: &Room, level: u32) {
+        let mut tiles_within: Vec<(usize,usize)> = room.tiles_within().into_iter()
+            .filter(|tile|{
+                self.get_index(*tile)
+                    .map(|index|self.terrain[index] == EntityKind::Floor && self.actors[index].is_none())
+                    .unwrap_or(false)
+            })
+            .collect();
+        tiles_within.shuffle();
+
+        if room.player {
+            if let Some(player_pos) = tiles_within.pop() {
+                self.player_pos.replace(player_pos);
+            }
+        }
+
+        if room.exit {
+            if let Some(coordinates) = tiles_within.pop() {
+                let tile_index = self.get_index(coordinates).unwrap();
+                self.terrain[tile_index] = exit_kind(level);
+            }
+        }
+
+        if room.loot {
+            if let Some(coordinates) = tiles_within.pop() {
+                let tile_index = self.get_index(coordinates).unwrap();
+
+                if let Some(item) = choose_item(level) {
+                    self.items[tile_index].push(item);
+                }
+            }
+        }
+
+        for _ in 0..gen_range(0,3) as i32 {
+            if let Some(enemy) = choose_enemy(level) {
+                if let Some(tile) = tiles_within.pop() {
+                    if let Ok(index) = self.get_index(tile) {
+                        self.actors[index].replace(enemy);
+                    }
+                }
+            }
+        }
+    }
+
+    //an L shaped hallway between two tiles, with doors where it goes
+    //through the walls of the rooms at either end
+    pub fn dig_hallway(&mut self, start: (usize,usize), finish: (usize,usize), ends: [&Room;2]) {
+        let (start,finish) = if start.0 > finish.0 {
+            (finish,start)
+        } else {
+            (start,finish)
+        };
+
+        let mut tiles: HashSet<(usize,usize)> = HashSet::new();
+        for x in start.0..=finish.0 {
+            tiles.insert((x,start.1));
+        }
+        for y in start.1.min(finish.1)..=start.1.max(finish.1) {
+            tiles.insert((finish.0,y));
+        }
+
+        for tile in tiles {
+            if let Ok(index) = self.get_index(tile) {
+                if self.terrain[index] == EntityKind::Wall {
+                    if ends.iter().any(|room|room.is_border(tile)) {
+                        self.terrain[index] = EntityKind::Door;
+                    } else {
+                        self.terrain[index] = EntityKind::Floor;
+                    }
+                }
+            }
+        }
+    }
+
+    //copies the vault onto the map with its top left corner at pos
+    pub fn stamp_vault(&mut self, vault: &Vault, pos: (usize,usize), level: u32) {
+        for y in 0..vault.height {
+            for x in 0..vault.width {
+                if let Ok(index) = self.get_index((pos.0+x,pos.1+y)) {
+                    let tile = vault.tiles[x+y*vault.width];
+                    self.terrain[index] = match tile {
+                        '#' => EntityKind::Wall,
+                        '+' => EntityKind::Door,
+                        _ => EntityKind::Floor,
+                    };
+                    match tile {
+                        'm' => self.actors[index] = choose_enemy(level),
+                        '*' => self.items[index].extend(choose_item(level)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
     pub fn fill_room_with_actors(
         &mut self,
         pos:(usize,usize),
         size:(usize,usize),
-        mut actors: Vec<EntityKind>
+        mut actors: Vec<String>
     ) -> Result<(),String> {
         let mut rect_indexes = self.rect(pos,size)?;
         rect_indexes.shuffle();
@@ -159,7 +339,7 @@ impl DungeonMap {
         &mut self,
         pos:(usize,usize),
         size:(usize,usize),
-        mut items: Vec<EntityKind>
+        mut items: Vec<String>
     ) -> Result<(),String> {
         let mut rect_indexes = self.rect(pos,size)?;
         rect_indexes.shuffle();
@@ -187,7 +367,7 @@ impl Room {
         (gen_range(x,x+w),gen_range(y,y+h))
     }
 
-    fn tiles_within(&self) -> Vec<(usize,usize)> {
+    pub fn tiles_within(&self) -> Vec<(usize,usize)> {
         let (room_x,room_y) = self.pos;
         let (w,h) = self.size;
         let mut vec = Vec::new();
@@ -232,20 +412,97 @@ impl Room {
     }
 }
 
+//anything that can lay out a floor, the same seed always builds the same map
+pub trait MapGenerator {
+    fn build(&self) -> DungeonMap;
+}
+
+//which generator lays out a floor, generators.ron says which ones can
+//come up at each depth
+#[derive(Clone,Debug,Deserialize)]
+pub enum Generator {
+    //the 3x3 rooms from dungeon_templates.ron
+    Quadrant,
+    Caves{width: usize, height: usize},
+    Bsp{width: usize, height: usize},
+}
+
+//caves and bsp floors can't be smaller than this either way
+pub const MIN_GENERATED_SIZE: usize = 16;
+
+//digging through a wall costs this many steps of walking around it
+const CARVE_COST: u32 = 8;
+//a floor that can't be finished is laid out again with a new seed this
+//many times before a way through is dug instead
+const MAX_REBUILDS: u64 = 5;
+
+//lays out a floor the player can always finish
+pub fn build_floor(level: u32, seed: u64) -> DungeonMap {
+    let mut map = lay_out_floor(level,seed);
+    for attempt in 1..=MAX_REBUILDS {
+        if map.unreachable().is_empty() {
+            return map;
+        }
+        map = lay_out_floor(level,seed.wrapping_add(attempt));
+    }
+    for tile in map.unreachable() {
+        map.carve_to(tile);
+    }
+    map
+}
+
+//picks a generator for the floor's depth and lays the floor out
+fn lay_out_floor(level: u32, seed: u64) -> DungeonMap {
+    srand(seed);
+    let generator: Box<dyn MapGenerator> = match choose_generator(level).unwrap_or(Generator::Quadrant) {
+        Generator::Quadrant => {
+            let mut template = choose_dungeon_template().unwrap();
+            if gen_range(0,2) as u32 == 0 {
+                template.transpose();
+            }
+            for _ in 0..gen_range(0,4) as u32 {
+                template.rotate();
+            }
+            Box::new(QuadrantMap::new(level,template,seed))
+        }
+        Generator::Caves{width,height} => Box::new(CaveMap{width,height,level,seed}),
+        Generator::Bsp{width,height} => Box::new(BspMap{width,height,level,seed}),
+    };
+    generator.build()
+}
+
+//the top floor has the way out of the tower instead of more stairs
+pub fn exit_kind(level: u32) -> EntityKind {
+    if level == 10 {
+        EntityKind::Exit
+    } else {
+        EntityKind::Stairs
+    }
+}
+
+//a hand drawn room from vaults.txt, tiles are the characters row by row
+#[derive(Clone,Debug)]
+pub struct Vault {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<char>,
+}
+
 pub struct QuadrantMap {
     doors: [bool;12],
     enter: usize,
     exit: usize,
-    difficulty: u32,
-    loot: u32,
     danger_rooms: Vec<usize>,
     treasure_rooms: Vec<usize>,
     level: u32,
+    seed: u64,
+    cell_size: usize,
 }
 
 
 impl QuadrantMap {
-    pub fn new(level: u32, template: DungeonTemplate )-> Self {
+    pub fn new(level: u32, template: DungeonTemplate, seed: u64)-> Self {
+        srand(seed);
         let enter = template.entrance;
         let exit = template.exit;
         let mut doors = [false;12];
@@ -256,25 +513,30 @@ impl QuadrantMap {
             doors,
             exit,
             enter,
-            difficulty: level,
-            loot: level,
             danger_rooms: template.danger_rooms,
             treasure_rooms: vec![gen_range(0,8)],
             level,
+            seed,
+            cell_size: template.cell_size,
         }
     }
+}
 
-    pub fn build(&self) -> DungeonMap {
-        let mut map = DungeonMap::new(22,22,EntityKind::Wall);
+impl MapGenerator for QuadrantMap {
+    fn build(&self) -> DungeonMap {
+        srand(self.seed);
+        let cell = self.cell_size;
+        let size = map_size(cell);
+        let mut map = DungeonMap::new(size,size,EntityKind::Wall);
         
         let mut rooms: Vec<Room> = Vec::new();
 
         for quadrant_id in 0..9 {
-            let (quad_x,quad_y) = quadrant_xy(quadrant_id).unwrap();
-            let w = gen_range(3,6);
-            let h = gen_range(3,6);
-            let x = quad_x+ 1 + gen_range(1,6-w);
-            let y = quad_y+ 1 + gen_range(1,6-h);
+            let (quad_x,quad_y) = quadrant_xy(quadrant_id,cell).unwrap();
+            let w = gen_range(3,cell-1);
+            let h = gen_range(3,cell-1);
+            let x = quad_x+ 1 + gen_range(1,cell-1-w);
+            let y = quad_y+ 1 + gen_range(1,cell-1-h);
             rooms.push(Room{
                 pos: (x,y),
                 size: (w,h),
@@ -286,51 +548,7 @@ impl QuadrantMap {
         }
         for room in rooms.iter() {
             map.fill_rect(EntityKind::Floor,room.pos,room.size).ok();
-            let mut tiles_within = room.tiles_within();
-            tiles_within.shuffle();
-
-            if room.player {
-                let player_pos = tiles_within.pop().unwrap();
-                map.player_pos.replace(player_pos);
-            }
-
-            if room.exit {
-                let coordinates = tiles_within.pop().unwrap();
-                let tile_index = map.get_index(coordinates).unwrap();
-
-                if self.level == 10 {
-                    map.terrain[tile_index] = EntityKind::Exit;
-                } else {
-                    map.terrain[tile_index] = EntityKind::Stairs;
-                }
-
-            }
-
-            if room.loot {
-                let coordinates = tiles_within.pop().unwrap();
-                let tile_index = map.get_index(coordinates).unwrap();
-
-                let items: Vec<EntityKind> = ITEMS.iter().map(|k|k.clone()).collect();
-
-                map.items[tile_index].push(items.choose().unwrap().clone());
-            }
-
-            let enemy_list: Vec<EntityKind> = ENEMIES.iter()
-                .take(self.difficulty as usize + 1)
-                .map(|k|k.clone())
-                .collect();
-
-
-            for _ in 0..gen_range(0,3) as i32 {
-                let enemy = enemy_list.choose().unwrap();
-                if let Some(tile) = tiles_within.pop() {
-                    if let Ok(index) = map.get_index(tile) {
-                        map.actors[index].replace(*enemy);
-                    }
-                }
-            }
-
-
+            map.furnish_room(room,self.level);
         }
 
         let mut hallways: Vec<(usize,(usize,usize),usize,(usize,usize))> = Vec::new();
@@ -345,60 +563,31 @@ impl QuadrantMap {
         }
 
         for (q1,start,q2,finish) in hallways {
-            let mut start = start; 
-            let mut finish = finish;
-            let room1 = &rooms[q1];
-            let room2 = &rooms[q2];
-            if start.0 > finish.0 {
-                let placeholder = start;
-                start = finish;
-                finish = placeholder;
-            }
-
-            let mut tiles: HashSet<(usize,usize)> = HashSet::new();
-            for x in start.0..=finish.0 {
-                tiles.insert((x,start.1));
-            }
-            if start.1 > finish.1 {
-                for y in finish.1..=start.1 {
-                    tiles.insert((finish.0,y));
-                }
-            } else {
-                for y in start.1..=finish.1 {
-                    tiles.insert((finish.0,y));
-                }
-            }
-
-            for tile in tiles {
-                if let Ok(index) = map.get_index(tile) {
-                    if map.terrain[index] == EntityKind::Wall {
-                        if room1.is_border(tile)||room2.is_border(tile){
-                            map.terrain[index] = EntityKind::Door;
-                        } else {
-                            map.terrain[index] = EntityKind::Floor;
-                        }
-                    }
-                }
-            }
+            map.dig_hallway(start,finish,[&rooms[q1],&rooms[q2]]);
         }
 
         map
     }
+}
 
+//each floor of a run gets its own seed, so a floor doesn't depend on
+//how many dice were rolled on the floors before it
+pub fn level_seed(seed: u64, level: u32) -> u64 {
+    seed.wrapping_mul(6364136223846793005)
+        .wrapping_add(level as u64)
+        .wrapping_mul(1442695040888963407)
 }
 
-fn quadrant_xy(quadrant: usize) -> Option<(usize,usize)> {
-    match quadrant {
-        0 => Some((0,  0 )),
-        1 => Some((7,  0 )),
-        2 => Some((14, 0 )),
-        3 => Some((0,  7 )),
-        4 => Some((7,  7 )),
-        5 => Some((14, 7 )),
-        6 => Some((0,  14)),
-        7 => Some((7,  14)),
-        8 => Some((14, 14)),
-        _ => {None}
+//the floor is a 3x3 grid of cells with a wall along the far edges
+pub fn map_size(cell_size: usize) -> usize {
+    cell_size*3+1
+}
+
+fn quadrant_xy(quadrant: usize, cell_size: usize) -> Option<(usize,usize)> {
+    if quadrant < 9 {
+        Some((quadrant%3*cell_size,quadrant/3*cell_size))
+    } else {
+        None
     }
 }
 
@@ -422,16 +611,47 @@ fn connected_quadrants(border_index:usize) -> Option<(usize,usize)> {
 
 
 //pub type DungeonTemplate = (usize,usize,[u8;12]);
-#[derive(Clone)]
+#[derive(Clone,Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct DungeonTemplate {
     pub entrance: usize,
     pub exit: usize,
     pub doors: [u8;12],
     pub treasure_rooms: Vec<usize>,
     pub danger_rooms: Vec<usize>,
+    //how many tiles across each of the 9 cells is, rooms grow with it
+    #[serde(default="default_cell_size")]
+    pub cell_size: usize,
+}
+
+pub const MIN_CELL_SIZE: usize = 7;
+
+fn default_cell_size() -> usize {
+    MIN_CELL_SIZE
 }
 
 impl DungeonTemplate {
+    //the rooms the doors don't join up to the entrance
+    pub fn unreachable_rooms(&self) -> Vec<usize> {
+        let reachable: HashSet<usize> = bfs_reach(self.entrance,|room|{
+            let room = *room;
+            self.doors.iter().enumerate()
+                .filter(|(_,door)|**door > 0)
+                .filter_map(|(i,_)|connected_quadrants(i))
+                .filter_map(move |(q1,q2)|{
+                    if q1 == room {
+                        Some(q2)
+                    } else if q2 == room {
+                        Some(q1)
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Vec<usize>>()
+        }).collect();
+        (0..9).filter(|room|!reachable.contains(room)).collect()
+    }
+
     pub fn rotate(&mut self) {
         let rotate_index = |i| -> Option<usize> {
             match i {
@@ -1,7 +1,15 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
 use hecs::*;
-use macroquad::color::*;
+use lazy_static::lazy_static;
+use serde::Deserialize;
 use crate::components::*;
 use crate::combat::*;
+use crate::lists::parse_data;
+use crate::constants::*;
+use crate::get_cloned;
+
+pub const ENTITY_TEMPLATES_PATH: &str = "data/entities.ron";
 
 #[derive(Copy,Clone,PartialEq,Debug)]
 pub enum EntityKind {
@@ -30,449 +38,294 @@ pub enum EntityKind {
     ThrowingSpear,
     HealthPotion,
     MagicMapping,
+    Arrows,
     //Scroll,
 
 }
 
-pub fn spawn_at(world:&mut World, kind: EntityKind, pos:Pos) -> Entity {
-    let ent = spawn(world,kind);
-    world.insert_one(ent,pos).ok();
-    ent
+impl EntityKind {
+    pub const ALL: [EntityKind;20] = [
+        EntityKind::Player,
+        EntityKind::Floor,
+        EntityKind::Door,
+        EntityKind::Wall,
+        EntityKind::Stairs,
+        EntityKind::Exit,
+        EntityKind::Rat,
+        EntityKind::Bat,
+        EntityKind::Zombie,
+        EntityKind::Wizard,
+        EntityKind::Centipede,
+        EntityKind::Goblin,
+        EntityKind::Gnome,
+        EntityKind::Sword,
+        EntityKind::Axe,
+        EntityKind::Armor,
+        EntityKind::ThrowingSpear,
+        EntityKind::HealthPotion,
+        EntityKind::MagicMapping,
+        EntityKind::Arrows,
+    ];
+
+    //the key of this kind's entry in the entity templates file
+    pub fn template_id(&self) -> &'static str {
+        match self {
+            EntityKind::Player => "Player",
+            EntityKind::Floor => "Floor",
+            EntityKind::Door => "Door",
+            EntityKind::Wall => "Wall",
+            EntityKind::Stairs => "Stairs",
+            EntityKind::Exit => "Exit",
+            EntityKind::Rat => "Rat",
+            EntityKind::Bat => "Bat",
+            EntityKind::Zombie => "Zombie",
+            EntityKind::Wizard => "Wizard",
+            EntityKind::Centipede => "Centipede",
+            EntityKind::Goblin => "Goblin",
+            EntityKind::Gnome => "Gnome",
+            EntityKind::Sword => "Sword",
+            EntityKind::Axe => "Axe",
+            EntityKind::Armor => "Armor",
+            EntityKind::ThrowingSpear => "ThrowingSpear",
+            EntityKind::HealthPotion => "HealthPotion",
+            EntityKind::MagicMapping => "MagicMapping",
+            EntityKind::Arrows => "Arrows",
+        }
+    }
 }
 
-pub fn spawn(world:&mut World, kind:EntityKind) -> Entity {
-    match kind {
-        EntityKind::Player => {world.spawn((
-            Player::new(),
-            Appearance{
-                sprite: 15,
-                color: BLUE,
-                layer: 10,
-                ..Default::default()
-            },
-            Health::new(15),
-            Name{
-                name: "Player".to_owned(),
-                description: "This is you".to_owned()
-            },
-            Equipment{
-                weapon: None,
-                armor: None,
-            },
-            Weapon {
-                attack: AttackData {
-                    range: Range::Meele,
-                    damage_low: 1,
-                    damage_high: 2,
-                    to_hit: 0,
-                    ..Default::default()
+//the components an entity is built from, as written in the templates file
+#[derive(Clone,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntityTemplate {
+    #[serde(default)]
+    pub name: Option<Name>,
+    #[serde(default)]
+    pub appearance: Option<Appearance>,
+    #[serde(default)]
+    pub health: Option<i32>,
+    #[serde(default)]
+    pub weapon: Option<AttackData>,
+    #[serde(default)]
+    pub defense: Option<Defense>,
+    //what wearing this adds to the wearer's defense
+    #[serde(default)]
+    pub armor: Option<Defense>,
+    #[serde(default)]
+    pub behavior: Option<Behavior>,
+    //actors without a speed move at NORMAL_SPEED
+    #[serde(default)]
+    pub speed: Option<i32>,
+    //actors without a light see FOV_DISTANCE
+    #[serde(default)]
+    pub light: Option<u32>,
+    #[serde(default)]
+    pub bump: Option<Bump>,
+    #[serde(default)]
+    pub equipable: Option<Equipable>,
+    #[serde(default)]
+    pub useable: Option<Useable>,
+    #[serde(default)]
+    pub weight: Option<u32>,
+    #[serde(default)]
+    pub ammo: bool,
+    //items with a stack size pile up with others from the same template
+    #[serde(default)]
+    pub stack: Option<u32>,
+    #[serde(default)]
+    pub equipment: bool,
+    //templates of items the entity spawns with equipped
+    #[serde(default)]
+    pub carries: Vec<String>,
+    #[serde(default)]
+    pub item: bool,
+    #[serde(default)]
+    pub blocks_sight: bool,
+    #[serde(default)]
+    pub player: bool,
+    #[serde(default = "default_on_level")]
+    pub on_level: bool,
+}
 
-                }
-            }
-        ))},
+fn default_on_level() -> bool {
+    true
+}
 
+lazy_static! {
+    static ref TEMPLATES: RwLock<HashMap<String,EntityTemplate>> = RwLock::new(HashMap::new());
+}
+
+//parses a templates file and replaces the current templates with it,
+//file_name is only used to point error messages at the right file
+pub fn load_templates(source: &str, file_name: &str) -> Result<(),String> {
+    let templates: HashMap<String,EntityTemplate> = parse_data(source,file_name)?;
 
-        // Enemies
-        EntityKind::Zombie => {world.spawn((
-            OnLevel,
-            Name{
-                name: "Zombie".to_owned(),
-                description: "".to_owned()
-            },
-            Appearance{
-                sprite: 10,
-                color: DARKGREEN,
-                layer: 9,
-                ..Default::default()
-            },
-            Health::new(5),
-            Bump::Attack,
-            Behavior::Slow(50),
-            Weapon{
-                attack: AttackData {
-                    range: Range::Meele,
-                    damage_low: 2,
-                    damage_high: 4,
-                    to_hit: -3,
-                    ..Default::default()
-                }
-            },
-            Defense {
-                dodging:-8,
-                armor:2,
+    //checked in order so the same broken file always reports the same error
+    let mut ids: Vec<&String> = templates.keys().collect();
+    ids.sort();
+    for id in ids {
+        let template = &templates[id];
+        let at = format!("{}:{}",file_name,template_line(source,id));
+        if let Some(health) = template.health {
+            if health <= 0 {
+                return Err(format!("{}: `{}` has {} health, it needs at least 1",at,id,health));
             }
-        )) },
-        EntityKind::Wizard => {world.spawn((
-            OnLevel,
-            Name{
-                name: "Wizard".to_owned(),
-                description: "".to_owned()
-            },
-            Appearance{
-                sprite: 13,
-                color: DARKGREEN,
-                layer: 9,
-                ..Default::default()
-            },
-            Health::new(2),
-            Bump::Attack,
-            Behavior::Erratic(10),
-            Weapon{
-                attack: AttackData {
-                    range: Range::Ranged(3),
-                    damage_low: 1,
-                    damage_high: 3,
-                    to_hit: -2,
-                    ..Default::default()
-                }
-            },
-            Defense {
-                dodging: -2,
-                armor: 0,
+        }
+        if let Some(speed) = template.speed {
+            if speed <= 0 {
+                return Err(format!("{}: `{}` has a speed of {}, it needs at least 1",at,id,speed));
             }
-        )) },
-        EntityKind::Centipede => {world.spawn((
-            OnLevel,
-            Name{
-                name: "Centipede".to_owned(),
-                description: "".to_owned()
-            },
-            Appearance{
-                sprite: 14,
-                color: YELLOW,
-                layer: 9,
-                ..Default::default()
-            },
-            Health::new(2),
-            Bump::Attack,
-            Behavior::Erratic(30),
-            Weapon{
-                attack: AttackData {
-                    range: Range::Meele,
-                    damage_low: 1,
-                    damage_high: 2,
-                    to_hit: 0,
-                    ..Default::default()
-                }
-            },
-            Defense {
-                dodging: 0,
-                armor: 1,
+        }
+        if let Some(weapon) = &template.weapon {
+            if weapon.damage_low > weapon.damage_high {
+                return Err(format!("{}: `{}` has a damage_low greater than its damage_high",at,id));
             }
-        )) },
-        EntityKind::Goblin => {world.spawn((
-            OnLevel,
-            Name{
-                name: "Goblin".to_owned(),
-                description: "".to_owned()
-            },
-            Appearance{
-                sprite: 16,
-                color: GREEN,
-                layer: 9,
-                ..Default::default()
-            },
-            Health::new(5),
-            Bump::Attack,
-            Behavior::Erratic(10),
-            Weapon{
-                attack: AttackData {
-                    range: Range::Meele,
-                    damage_low: 1,
-                    damage_high: 2,
-                    to_hit: 0,
-                    ..Default::default()
-                }
-            },
-            Defense {
-                dodging: -3,
-                armor: 2,
+        }
+        //an attack that costs nothing never lets the monsters have a turn
+        let attacks = template.weapon.iter().chain(match &template.useable {
+            Some(Useable::Fireball(attack)) | Some(Useable::Throw(attack,_)) => Some(attack),
+            _ => None,
+        });
+        for attack in attacks {
+            if attack.cost <= 0 {
+                return Err(format!("{}: `{}` has an attack cost of {}, it needs at least 1",at,id,attack.cost));
             }
-        )) },
-        EntityKind::Gnome => {world.spawn((
-            OnLevel,
-            Name{
-                name: "Gnome".to_owned(),
-                description: "".to_owned()
-            },
-            Appearance{
-                sprite: 17,
-                color: RED,
-                layer: 9,
-                ..Default::default()
-            },
-            Health::new(2),
-            Bump::Attack,
-            Behavior::Erratic(50),
-            Weapon{
-                attack: AttackData {
-                    range: Range::Ranged(3),
-                    damage_low: 1,
-                    damage_high: 1,
-                    to_hit: 4,
-                    ..Default::default()
-                }
-            },
-            Defense {
-                dodging: 0,
-                armor: 0,
+        }
+        if template.stack == Some(0) {
+            return Err(format!("{}: `{}` has a stack of 0, it needs at least 1",at,id));
+        }
+        if let (Some(Equipable::Armor),None) = (template.equipable,&template.armor) {
+            return Err(format!("{}: `{}` is equipable as armor but has no armor",at,id));
+        }
+        for carried in template.carries.iter() {
+            match templates.get(carried) {
+                Some(item) if item.equipable.is_some() => {}
+                Some(_) => return Err(format!("{}: `{}` carries `{}`, which can't be equipped",at,id,carried)),
+                None => return Err(format!("{}: `{}` carries `{}`, which has no template",at,id,carried)),
             }
-        )) },
-        EntityKind::Bat => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Bat".to_owned(),
-                description: "".to_owned()
-            },
-            Appearance{
-                sprite: 12,
-                color: GRAY,
-                layer: 9,
-                ..Default::default()
-            },
-            Health::new(1),
-            Bump::Attack,
-            Behavior::Erratic(50),
-            Weapon{
-                attack: AttackData {
-                    range: Range::Meele,
-                    damage_low: 1,
-                    damage_high: 1,
-                    to_hit: -1,
-                    ..Default::default()
-                }
-            },
-            Defense {
-                dodging: 1,
-                armor: 0
-            },
-        )) },
-        EntityKind::Rat => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Rat".to_owned(),
-                description: "".to_owned()
-            },
-            Appearance{
-                sprite: 11,
-                color: DARKBROWN,
-                layer: 9,
-                ..Default::default()
-            },
-            Health::new(1),
-            Bump::Attack,
-            Behavior::Erratic(20),
-            Weapon{
-                attack: AttackData {
-                    range: Range::Meele,
-                    damage_low: 1,
-                    damage_high: 2,
-                    to_hit: 0,
-                    ..Default::default()
-                }
-            },
-        )) },
+        }
+    }
 
+    for kind in EntityKind::ALL.iter() {
+        if !templates.contains_key(kind.template_id()) {
+            return Err(format!("{}: missing a template for `{}`",file_name,kind.template_id()));
+        }
+    }
 
+    *TEMPLATES.write().unwrap() = templates;
+    Ok(())
+}
 
-        //items
-        EntityKind::Sword => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Sword".to_owned(),
-                description: "".to_owned()
-            },
-            Item,
-            Appearance{
-                sprite: 20,
-                color: LIGHTGRAY,
-                layer: 5,
-                ..Default::default()
-            },
-            Equipable::Weapon,
-            Weapon {
-                attack: AttackData {
-                    range: Range::Meele,
-                    damage_low: 2,
-                    damage_high: 3,
-                    to_hit: 5,
-                    ..Default::default()
-                }
-            }
-        )) }
-        EntityKind::Axe => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Axe".to_owned(),
-                description: "".to_owned()
-            },
-            Item,
-            Appearance{
-                sprite: 21,
-                color: LIGHTGRAY,
-                layer: 5,
-                ..Default::default()
-            },
-            Equipable::Weapon,
-            Weapon {
-                attack: AttackData {
-                    range: Range::Meele,
-                    damage_low: 1,
-                    damage_high: 3,
-                    to_hit: 2,
-                    axe: true,
-                    ..Default::default()
-                }
-            }
-        )) }
-        EntityKind::Armor => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Armor".to_owned(),
-                description: "".to_owned()
-            },
-            Item,
-            Appearance{
-                sprite: 23,
-                color: LIGHTGRAY,
-                layer: 5,
-                ..Default::default()
-            },
-            Equipable::Armor,
-            Defense{
-                dodging: -3,
-                armor: 3,
-            }
-        )) }
+//the line a template's id is on, for errors in its values which ron can't
+//point at once the file has parsed
+fn template_line(source: &str, id: &str) -> usize {
+    let key = format!("\"{}\"",id);
+    source.lines()
+        .position(|line|line.trim_start().starts_with(&key))
+        .map(|i|i+1)
+        .unwrap_or(0)
+}
 
+pub fn template_exists(id: &str) -> bool {
+    TEMPLATES.read().unwrap().contains_key(id)
+}
 
-        EntityKind::HealthPotion => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Health Potion".to_owned(),
-                description: "".to_owned()
-            },
-            Item,
-            Appearance{
-                sprite: 30,
-                color: RED,
-                layer: 5,
-                ..Default::default()
-            },
-            Useable::Heal,
-        )) }
-        EntityKind::ThrowingSpear => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Throwing Spear".to_owned(),
-                description: "".to_owned()
-            },
-            Item,
-            Appearance{
-                sprite: 22,
-                color: WHITE,
-                layer: 5,
-                ..Default::default()
-            },
-            Useable::Throw(AttackData {
-                range: Range::Ranged(10),
-                damage_low: 1,
-                damage_high: 2,
-                to_hit: 4,
-                axe: false,
-                ..Default::default()
-            },false),
-        )) }
-        EntityKind::MagicMapping => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Scroll of Mapping".to_owned(),
-                description: "".to_owned()
-            },
-            Item,
-            Appearance{
-                sprite: 31,
-                color: WHITE,
-                layer: 5,
-                ..Default::default()
-            },
-            Useable::MagicMapping,
-        )) }
+pub fn spawn_at(world:&mut World, kind: EntityKind, pos:Pos) -> Entity {
+    let ent = spawn(world,kind);
+    world.insert_one(ent,pos).ok();
+    ent
+}
 
-        //Terrain
-        EntityKind::Floor => { world.spawn((
-            OnLevel,
-            Appearance{
-                sprite: 1,
-                color: GRAY,
-                layer: 1,
-                ignore_overlap: true,
-                ..Default::default()
-            },
-        ))}
-        EntityKind::Door => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Door".to_owned(),
-                description: "".to_owned()
-            },
-            Appearance{
-                sprite:6,
-                color: BROWN,
-                layer:5,
-                ..Default::default()
-            },
-            Bump::OpenDoor,
-            BlocksSight,
-        ))}
+pub fn spawn(world:&mut World, kind:EntityKind) -> Entity {
+    spawn_template(world,kind.template_id())
+}
 
-        EntityKind::Wall => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Wall".to_owned(),
-                description: "".to_owned()
-            },
-            Appearance{
-                sprite: 2,
-                color: GRAY,
-                layer: 5,
-                ..Default::default()
-            },
-            Bump::BlocksMovement,
-            BlocksSight,
-        ))}
+pub fn spawn_template(world:&mut World, id: &str) -> Entity {
+    let templates = TEMPLATES.read().unwrap();
+    let template = templates.get(id)
+        .unwrap_or_else(||panic!("no entity template named `{}`",id));
 
-        EntityKind::Stairs => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Stairs".to_owned(),
-                description: "".to_owned()
-            },
-            Appearance{
-                sprite: 4,
-                color: WHITE,
-                layer: 5,
-                ..Default::default()
-            },
-            Bump::NextLevel,
-        ))}
+    let mut builder = EntityBuilder::new();
+    if template.player {
+        builder.add(Player::new());
+    }
+    if template.on_level {
+        builder.add(OnLevel);
+    }
+    if template.item {
+        builder.add(Item);
+    }
+    if template.blocks_sight {
+        builder.add(BlocksSight);
+    }
+    if template.equipment || !template.carries.is_empty() {
+        builder.add(Equipment{
+            weapon: None,
+            armor: None,
+        });
+    }
+    if let Some(name) = &template.name {
+        builder.add(name.clone());
+    }
+    if let Some(appearance) = &template.appearance {
+        builder.add(appearance.clone());
+    }
+    if let Some(health) = template.health {
+        builder.add(Health::new(health));
+    }
+    if let Some(attack) = &template.weapon {
+        builder.add(Weapon{
+            attack: attack.clone(),
+        });
+    }
+    if let Some(defense) = &template.defense {
+        builder.add(defense.clone());
+    }
+    if let Some(defense) = &template.armor {
+        builder.add(Armor{defense: defense.clone()});
+    }
+    if let Some(behavior) = template.behavior {
+        builder.add(behavior);
+    }
+    if template.player || template.behavior.is_some() {
+        builder.add(Energy::new(template.speed.unwrap_or(NORMAL_SPEED)));
+    }
+    if let Some(radius) = template.light {
+        builder.add(Light{radius});
+    }
+    if let Some(bump) = template.bump {
+        builder.add(bump);
+    }
+    if let Some(equipable) = template.equipable {
+        builder.add(equipable);
+    }
+    if let Some(useable) = &template.useable {
+        builder.add(useable.clone());
+    }
+    if let Some(weight) = template.weight {
+        builder.add(Weight{weight});
+    }
+    if template.ammo {
+        builder.add(Ammo);
+    }
+    if let Some(count) = template.stack {
+        builder.add(Stack{template: id.to_owned(),count});
+    }
+    let carries = template.carries.clone();
+    drop(templates);
 
-        EntityKind::Exit => { world.spawn((
-            OnLevel,
-            Name{
-                name: "Exit".to_owned(),
-                description: "".to_owned()
-            },
-            Appearance{
-                sprite: 8,
-                color: WHITE,
-                layer: 5,
-                ..Default::default()
-            },
-            Bump::NextLevel,
-        ))}
+    let ent = world.spawn(builder.build());
+    //carried items stay on the level without a position until dropped
+    for id in carries.iter() {
+        let item = spawn_template(world,id);
+        let equipable = get_cloned::<Equipable>(world,item);
+        if let Ok(mut equipment) = world.get_mut::<Equipment>(ent) {
+            match equipable {
+                Some(Equipable::Weapon) => {equipment.weapon.replace(item);}
+                Some(Equipable::Armor) => {equipment.armor.replace(item);}
+                None => {}
+            }
+        }
     }
+    ent
 }
-
-
-
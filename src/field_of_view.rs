@@ -1,12 +1,14 @@
 use std::collections::HashSet;
 use hecs::*;
 use crate::prelude::*;
-use bresenham::Bresenham;
 
 pub fn update_fov(world: &mut World,resources: &mut Resources,magic_mapping: bool) {
     let player = player(world).unwrap();
-    let positions_in_fov = if let Ok(pos) = world.get::<Pos>(player) {
-        fov(world,(pos.x,pos.y),FOV_DISTANCE,magic_mapping)
+    let radius = sight_radius(world,player);
+    let positions_in_fov = if magic_mapping || DEBUG_FOV {
+        whole_level(resources)
+    } else if let Some(pos) = get_cloned::<Pos>(world,player) {
+        fov(world,&resources.spatial_index,(pos.x,pos.y),radius)
     } else {
         HashSet::new()
     };
@@ -15,7 +17,7 @@ pub fn update_fov(world: &mut World,resources: &mut Resources,magic_mapping: boo
 
     let mut new_memories = Vec::new();
     let mut dead_memories = Vec::new();
-    for (ent,(pos,mut appearance,memory)) in world.query::<(&Pos,&mut Appearance,Option<&PlayerMemory>)>().iter() {
+    for (ent,(pos,appearance,memory,name)) in world.query::<(&Pos,&mut Appearance,Option<&PlayerMemory>,Option<&Name>)>().iter() {
         if positions_in_fov.contains(&(pos.x,pos.y)) {
             if memory.is_some() {
                 dead_memories.push(ent.clone());
@@ -27,58 +29,246 @@ pub fn update_fov(world: &mut World,resources: &mut Resources,magic_mapping: boo
                 let mut appearance = appearance.clone();
                 appearance.in_fov = false;
                 
-                new_memories.push((
-                    PlayerMemory,
-                    pos.clone(),
-                    appearance,
-                    OnLevel,
-
-                ));
+                //the name is kept so remembered tiles can still be looked at
+                let mut memory = EntityBuilder::new();
+                memory.add(PlayerMemory)
+                    .add(pos.clone())
+                    .add(appearance)
+                    .add(OnLevel);
+                if let Some(name) = name {
+                    memory.add(name.clone());
+                }
+                new_memories.push((*pos,memory));
             }
             appearance.in_fov = false;
         }
     }
-    dead_memories.iter().for_each(|&e|{world.despawn(e).ok();});
-    world.spawn_batch(new_memories);
+    dead_memories.iter().for_each(|&e|despawn(world,&mut resources.spatial_index,e));
+    for (pos,mut memory) in new_memories {
+        let ent = world.spawn(memory.build());
+        resources.spatial_index.insert(ent,(pos.x,pos.y));
+    }
+}
+
+
+//how far an actor can see, a blind one only sees what's right next to it
+pub fn sight_radius(world: &World, ent: Entity) -> u32 {
+    if has_status(world,ent,StatusKind::Blindness) {
+        1
+    } else {
+        get_cloned::<Light>(world,ent).map(|light|light.radius).unwrap_or(FOV_DISTANCE)
+    }
+}
+
+//whether the viewer can see the target with its own sight radius, this
+//is how monsters notice the player
+pub fn can_see(world: &World, resources: &Resources, viewer: Entity, target: Entity) -> bool {
+    let (from,to) = match (get_cloned::<Pos>(world,viewer),get_cloned::<Pos>(world,target)) {
+        (Some(from),Some(to)) => ((from.x,from.y),(to.x,to.y)),
+        _ => return false,
+    };
+    let radius = sight_radius(world,viewer) as i32;
+    let (dx,dy) = (to.0-from.0,to.1-from.1);
+    if dx*dx+dy*dy > radius*radius+radius {
+        return false;
+    }
+    fov(world,&resources.spatial_index,from,radius as u32).contains(&to)
 }
 
+fn whole_level(resources: &Resources) -> HashSet<(i32,i32)> {
+    resources.spatial_index.tiles().collect()
+}
 
-fn block_map(world: &World) -> HashSet<(i32,i32)> {
+//the tiles visible from origin within radius, found with symmetric
+//shadowcasting so a can see b exactly when b can see a
+//https://www.albertford.com/shadowcasting/
+pub fn fov(world: &World, index: &SpatialIndex, origin: (i32,i32), radius: u32) -> HashSet<(i32,i32)> {
+    let radius = radius as i32;
     let mut set = HashSet::new();
-    for (_,(pos,_)) in world.query::<(&Pos,&BlocksSight)>().iter() {
-        set.insert((pos.x,pos.y));
+    set.insert(origin);
+
+    //each quadrant is scanned as rows moving away from the origin, with
+    //col running across the row
+    let quadrants: [Transform;4] = [
+        |(x,y),depth,col|(x+col,y-depth),
+        |(x,y),depth,col|(x+col,y+depth),
+        |(x,y),depth,col|(x+depth,y+col),
+        |(x,y),depth,col|(x-depth,y+col),
+    ];
+    for transform in quadrants.iter() {
+        let mut rows = vec![Row{depth: 1,start: Slope::new(-1,1),end: Slope::new(1,1)}];
+        while let Some(mut row) = rows.pop() {
+            if row.depth > radius {
+                continue;
+            }
+            let mut previous_was_wall: Option<bool> = None;
+            for col in row.min_col()..=row.max_col() {
+                let tile = transform(origin,row.depth,col);
+                let wall = index.at(tile).iter().any(|ent|world.get::<BlocksSight>(*ent).is_ok());
+                let in_radius = row.depth*row.depth+col*col <= radius*radius+radius;
+                if in_radius && (wall || row.is_symmetric(col)) {
+                    set.insert(tile);
+                }
+                match previous_was_wall {
+                    Some(true) if !wall => {
+                        row.start = Slope::new(2*col-1,2*row.depth);
+                    }
+                    Some(false) if wall => {
+                        let mut next = row.next();
+                        next.end = Slope::new(2*col-1,2*row.depth);
+                        rows.push(next);
+                    }
+                    _ => {}
+                }
+                previous_was_wall = Some(wall);
+            }
+            if previous_was_wall == Some(false) {
+                rows.push(row.next());
+            }
+        }
     }
     set
 }
 
-fn line_blocked(block_map: &HashSet<(i32,i32)>, a: (i32,i32), b: (i32,i32)) -> bool {
-    let a = (a.0 as isize,a.1 as isize);
-    let b = (b.0 as isize,b.1 as isize);
+//turns a (depth,col) in a quadrant into a tile, given the origin
+type Transform = fn((i32,i32),i32,i32)->(i32,i32);
+
+//an exact fraction, den is always positive
+#[derive(Copy,Clone)]
+struct Slope {
+    num: i32,
+    den: i32,
+}
+
+impl Slope {
+    fn new(num: i32, den: i32) -> Self {
+        Self {num,den}
+    }
+}
+
+#[derive(Copy,Clone)]
+struct Row {
+    depth: i32,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    //depth*start rounded with ties going up
+    fn min_col(&self) -> i32 {
+        (2*self.depth*self.start.num+self.start.den).div_euclid(2*self.start.den)
+    }
+
+    //depth*end rounded with ties going down
+    fn max_col(&self) -> i32 {
+        -(self.end.den-2*self.depth*self.end.num).div_euclid(2*self.end.den)
+    }
 
-    for (x,y) in Bresenham::new(a,b) {
-        if (x,y) != a && (x,y) != b && block_map.contains(&(x as i32,y as i32)) {
-            return true;
+    fn next(&self) -> Self {
+        Self {
+            depth: self.depth+1,
+            ..*self
         }
     }
 
-    false
+    //floor tiles only count as seen if they're inside the row's slopes, so
+    //the result doesn't depend on which end the scan started from
+    fn is_symmetric(&self, col: i32) -> bool {
+        col*self.start.den >= self.depth*self.start.num &&
+            col*self.end.den <= self.depth*self.end.num
+    }
 }
 
-fn fov(world: &World, origin: (i32,i32), distance: u32, magic_mapping: bool ) -> HashSet<(i32,i32)> {
-    let debug = magic_mapping || DEBUG_FOV;
-    let mut set = HashSet::new();
-    let distance = distance as i32;
-    let left = origin.0-distance;
-    let right = origin.0+distance+1;
-    let top = origin.1-distance;
-    let bot = origin.1+distance+1;
-    let block_map = block_map(world);
-    for x in left..right {
-        for y in top..bot {
-            if debug || !line_blocked(&block_map,origin,(x,y)) {
-                set.insert((x,y));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a world with a wall entity on every '#' of the rows, and the index
+    //that goes with it
+    fn walls(rows: &[&str]) -> (World,SpatialIndex) {
+        let mut world = World::new();
+        let mut index = SpatialIndex::new(rows[0].len(),rows.len());
+        for (y,row) in rows.iter().enumerate() {
+            for (x,c) in row.chars().enumerate() {
+                if c == '#' {
+                    let ent = world.spawn((Pos::new(x as i32,y as i32),BlocksSight));
+                    index.insert(ent,(x as i32,y as i32));
+                }
             }
         }
+        (world,index)
+    }
+
+    #[test]
+    fn symmetric() {
+        //walls scattered with a fixed lcg so every run checks the same map
+        let mut state: u64 = 1;
+        let rows: Vec<String> = (0..20).map(|_|{
+            (0..20).map(|_|{
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                if (state>>33)%4 == 0 {'#'} else {'.'}
+            }).collect()
+        }).collect();
+        let rows: Vec<&str> = rows.iter().map(|row|row.as_str()).collect();
+        let (world,index) = walls(&rows);
+
+        let floors: Vec<(i32,i32)> = index.tiles()
+            .filter(|tile|index.at(*tile).is_empty())
+            .collect();
+        let seen: Vec<HashSet<(i32,i32)>> = floors.iter()
+            .map(|tile|fov(&world,&index,*tile,FOV_DISTANCE))
+            .collect();
+        for (a,seen_from_a) in floors.iter().zip(seen.iter()) {
+            for (b,seen_from_b) in floors.iter().zip(seen.iter()) {
+                assert_eq!(seen_from_a.contains(b),seen_from_b.contains(a),"{:?} and {:?}",a,b);
+            }
+        }
+    }
+
+    #[test]
+    fn room_corners() {
+        let (world,index) = walls(&[
+            "#######",
+            "#.....#",
+            "#.....#",
+            "#.....#",
+            "#######",
+        ]);
+        let seen = fov(&world,&index,(2,2),FOV_DISTANCE);
+        for corner in [(0,0),(6,0),(0,4),(6,4)] {
+            assert!(seen.contains(&corner),"{:?}",corner);
+        }
+        assert_eq!(seen.len(),35);
+    }
+
+    #[test]
+    fn pillar_and_corridor() {
+        let (world,index) = walls(&[
+            "#########",
+            "#.......#",
+            "#...#...#",
+            "#.......#",
+            "#####.###",
+            "####...##",
+            "#########",
+        ]);
+        let seen = fov(&world,&index,(2,2),FOV_DISTANCE);
+        //straight behind the pillar is hidden, to the side of it isn't
+        assert!(seen.contains(&(4,2)));
+        assert!(!seen.contains(&(5,2)));
+        assert!(!seen.contains(&(7,2)));
+        assert!(seen.contains(&(5,1)));
+        //through the gap in the wall only the far side of the corridor
+        //below it can be seen, the near side is round the corner
+        assert!(seen.contains(&(6,5)));
+        assert!(!seen.contains(&(4,5)));
+    }
+
+    #[test]
+    fn radius() {
+        let (world,index) = walls(&["..........."]);
+        let seen = fov(&world,&index,(0,0),3);
+        assert!(seen.contains(&(3,0)));
+        assert!(!seen.contains(&(4,0)));
     }
-    set
 }
@@ -1,10 +1,11 @@
 use macroquad::prelude::*;
 use crate::prelude::*;
 use hecs::*;
+use crate::simulation::{carried_weight,CARRY_CAPACITY};
 
 pub fn draw_hud(world: &World, resources: &Resources) {
-    let tile_size = screen_height()/(ARENA_HEIGHT as f32+2.);
-    let hud_start = tile_size*(ARENA_WIDTH as f32+2.);
+    let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
+    let hud_start = tile_size*(VIEW_WIDTH as f32+2.);
     let hud_width = tile_size*10.;
     let text_size = (tile_size*0.8) as u16;
 
@@ -31,6 +32,10 @@ pub fn draw_hud(world: &World, resources: &Resources) {
     let bar_width = hud_width-(tile_size*2.);
     let bar_height = tile_size;
 
+    if let Ok(effects) = world.get::<StatusEffects>(player) {
+        draw_status_icons(&effects,hud_start+tile_size+bar_width,tile_size*1.1,tile_size*0.8,resources);
+    }
+
     //player health
     if let Ok(health) = world.get::<Health>(player) {
         draw_bar(Rect::new(
@@ -41,12 +46,26 @@ pub fn draw_hud(world: &World, resources: &Resources) {
             ,RED,health.current as f32,health.max as f32);
     }
 
-    
+    //carried weight, turns orange once it slows the player
+    let load = carried_weight(world,resources);
+    draw_text_ex(
+        format!("Load: {}/{}",load,CARRY_CAPACITY).as_str(),
+        hud_start+tile_size,3.8*tile_size,
+        TextParams {
+            font_size: text_size,
+            color: if load > CARRY_CAPACITY {ORANGE} else {LIGHTGRAY},
+            font: resources.font,
+            ..Default::default()
+        });
+
+    let log_top = screen_height()-tile_size*(LOG_HEIGHT+1.);
+
     //monster health
-    let mut monster_health_y = tile_size*3.;
-    for (_,(appearance,name,health)) in 
-        world.query::<(&Appearance,&Name,&Health)>().without::<Player>().iter() {
-            if appearance.in_fov {
+    let mut monster_health_y = tile_size*4.5;
+    for (_,(appearance,name,health,effects)) in 
+        world.query::<(&Appearance,&Name,&Health,Option<&StatusEffects>)>().without::<Player>().iter() {
+            //leave room for the message log
+            if appearance.in_fov && monster_health_y+tile_size*2. <= log_top {
 
                 draw_text_ex(
                     name.name.as_str(),
@@ -59,6 +78,10 @@ pub fn draw_hud(world: &World, resources: &Resources) {
                     }
                 );
 
+                if let Some(effects) = effects {
+                    draw_status_icons(effects,hud_start+tile_size+bar_width,monster_health_y+tile_size*0.1,tile_size*0.8,resources);
+                }
+
                 draw_bar(Rect::new(
                         hud_start+tile_size,
                         monster_health_y+tile_size,
@@ -70,6 +93,137 @@ pub fn draw_hud(world: &World, resources: &Resources) {
             }
     }
 
+    draw_message_log(resources,Rect::new(
+            hud_start+tile_size*0.5,
+            log_top,
+            hud_width-tile_size,
+            tile_size*LOG_HEIGHT),
+        tile_size);
+
+    draw_text_ex(
+        format!("Seed: {}",resources.seed).as_str(),
+        hud_start+tile_size,screen_height()-0.4*tile_size,
+        TextParams {
+            font_size: (tile_size*0.6) as u16,
+            color: GRAY,
+            font: resources.font,
+            ..Default::default()
+        });
+}
+
+//height of the message log panel in tiles
+pub const LOG_HEIGHT: f32 = 9.;
+
+fn draw_message_log(resources: &Resources, area: Rect, tile_size: f32) {
+    let font_size = (tile_size*0.5) as u16;
+    let line_height = tile_size*0.6;
+    let padding = tile_size*0.2;
+    let text_width = area.w-padding*2.;
+
+    draw_rectangle_lines(area.x,area.y,area.w,area.h,2.,DARKGRAY);
+
+    let log = &resources.message_log;
+    let title = if log.scroll > 0 {
+        format!("Log -{} (PgUp/PgDn)",log.scroll)
+    } else {
+        "Log".to_owned()
+    };
+    draw_text_ex(
+        title.as_str(),
+        area.x+padding,area.y+line_height,
+        TextParams {
+            font_size,
+            color: GRAY,
+            font: resources.font,
+            ..Default::default()
+        });
+
+    //newest lines at the bottom, wrapped to fit, until the panel is full
+    let max_rows = ((area.h-line_height-padding)/line_height) as usize;
+    let mut rows: Vec<(String,Color)> = Vec::new();
+    for line in log.lines.iter().rev().skip(log.scroll) {
+        let wrapped = wrap_text(&line.display(),text_width,resources.font,font_size);
+        for row in wrapped.into_iter().rev() {
+            rows.push((row,line.color));
+        }
+        if rows.len() >= max_rows {
+            break;
+        }
+    }
+    rows.truncate(max_rows);
+
+    let bottom = area.y+area.h-padding;
+    for (i,(row,color)) in rows.iter().enumerate() {
+        draw_text_ex(
+            row.as_str(),
+            area.x+padding,bottom-line_height*i as f32,
+            TextParams {
+                font_size,
+                color: *color,
+                font: resources.font,
+                ..Default::default()
+            });
+    }
+}
+
+pub fn wrap_text(text: &str, width: f32, font: Font, font_size: u16) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if row.is_empty() {
+            word.to_owned()
+        } else {
+            format!("{} {}",row,word)
+        };
+        if !row.is_empty() && measure_text(&candidate,Some(font),font_size,1.).width > width {
+            rows.push(row);
+            row = word.to_owned();
+        } else {
+            row = candidate;
+        }
+    }
+    if !row.is_empty() {
+        rows.push(row);
+    }
+    rows
+}
+
+//page up/down and the mouse wheel move back through older log lines
+pub fn scroll_message_log(resources: &mut Resources) {
+    let log = &mut resources.message_log;
+    if is_key_pressed(KeyCode::PageUp) {
+        log.scroll_by(5);
+    }
+    if is_key_pressed(KeyCode::PageDown) {
+        log.scroll_by(-5);
+    }
+    if is_key_pressed(KeyCode::End) {
+        log.scroll = 0;
+    }
+    let (_,wheel) = mouse_wheel();
+    if wheel > 0. {
+        log.scroll_by(1);
+    } else if wheel < 0. {
+        log.scroll_by(-1);
+    }
+}
+
+//one small lettered square per effect, lined up leftwards from right
+fn draw_status_icons(effects: &StatusEffects, right: f32, y: f32, size: f32, resources: &Resources) {
+    for (i,effect) in effects.effects.iter().enumerate() {
+        let (letter,color) = effect.kind.icon();
+        let x = right-size*(i+1) as f32-2.*i as f32;
+        draw_rectangle(x,y,size,size,color);
+        draw_text_ex(
+            letter,
+            x+size*0.25,y+size*0.8,
+            TextParams {
+                font_size: (size*0.9) as u16,
+                color: BLACK,
+                font: resources.font,
+                ..Default::default()
+            });
+    }
 }
 
 fn draw_bar(rect: Rect, color: Color, value: f32, max: f32) {
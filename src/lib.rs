@@ -0,0 +1,268 @@
+use macroquad::prelude::*;
+use hecs::*;
+
+
+pub mod resources;
+pub mod console;
+pub mod components;
+pub mod factory;
+pub mod dungeon_gen;
+pub mod cave_gen;
+pub mod bsp_gen;
+pub mod states;
+pub mod movement;
+pub mod combat;
+pub mod behavior;
+pub mod field_of_view;
+pub mod monster_memory;
+pub mod hud;
+pub mod lists;
+pub mod screen_messages;
+pub mod save;
+pub mod simulation;
+pub mod replay;
+pub mod message_log;
+pub mod status_effects;
+pub mod settings;
+pub mod spatial_index;
+
+pub mod prelude {
+    pub use crate::resources::Resources;
+    pub use crate::factory::*;
+    pub use crate::components::*;
+    pub use crate::{
+        world_to_console,
+        player,
+        draw_map_and_hud,
+        mouse_to_map,
+        camera,
+        map_to_screen,
+        get_entities_at,
+        //build_dungeon,
+        clear_hightlights,
+        get_cloned,
+    };
+    pub use crate::hud::{draw_hud,scroll_message_log};
+    pub use crate::movement::{Dir,bump_at,is_blocked,neighbours,walkable_tiles};
+    pub use crate::spatial_index::{SpatialIndex,set_pos,remove_pos,despawn};
+    pub use crate::constants::*;
+    pub use crate::behavior::act;
+    pub use crate::states::*;
+    pub use crate::field_of_view::{update_fov,can_see};
+    pub use crate::lists::*;
+    pub use crate::screen_messages::*;
+    pub use crate::message_log::*;
+    pub use crate::status_effects::*;
+}
+
+pub mod constants {
+    pub const TILE_WIDTH: f32 = 32.;
+    pub const TILE_HEIGHT: f32 = 32.;
+    pub const SPRITE_WIDTH: f32 = 32.;
+    pub const SPRITE_HEIGHT: f32 = 32.;
+    pub const SPRITE_SHEET_COLUMNS: u32 = 10;
+    //how many tiles of the map fit on screen, bigger floors scroll
+    pub const VIEW_WIDTH: usize = 22;
+    pub const VIEW_HEIGHT: usize = 22;
+    pub const HUD_WIDTH: usize = 320;
+    pub const WINDOW_WIDTH: i32 = (TILE_WIDTH as usize * (VIEW_WIDTH+2) + HUD_WIDTH) as i32;
+    pub const WINDOW_HEIGHT: i32 = (TILE_HEIGHT as usize * (VIEW_HEIGHT+2)) as i32;
+    //how far actors without a Light can see
+    pub const FOV_DISTANCE: u32 = 20;
+    //the energy cost of an ordinary action, and how much an actor of
+    //normal speed gains per tick
+    pub const ACTION_COST: i32 = 100;
+    pub const NORMAL_SPEED: i32 = 10;
+    pub const TICKS_PER_TURN: u64 = (ACTION_COST/NORMAL_SPEED) as u64;
+    pub const DEBUG_FOV: bool = false;
+}
+
+
+use prelude::*;
+use dungeon_gen::*;
+use console::AsciiConsole;
+
+pub fn world_to_console(
+    world:     &World,
+    resources: &Resources
+) -> AsciiConsole {
+    //the map console (later to be blitted onto the fram console)
+    let mut con = AsciiConsole::new(VIEW_WIDTH,VIEW_HEIGHT,None);
+    let camera = camera(world,resources);
+    let on_screen = |pos: &Pos| (pos.x-camera.0,pos.y-camera.1);
+
+    //collect draw data for entities in fov
+    let mut draw_data: Vec<(Pos,Appearance)> = world.query::<(&Pos,&Appearance)>().iter()
+        .map(|(_,(pos,appearance))|{
+            (pos.clone(),appearance.clone())
+        }).collect();
+    
+    draw_data.sort_by(|a,b|{
+        (a.1.layer).partial_cmp(&b.1.layer).unwrap()
+    });
+
+    //collect draw data for memories
+    let mut memory_draw_data: Vec<(Pos,Appearance)> = world.query::<(&Pos,&Appearance,&PlayerMemory)>().iter()
+        .map(|(_,(pos,appearance,_))|{
+            let mut appearance = appearance.clone();
+            appearance.color.a = 0.5;
+            (pos.clone(),appearance)
+        }).collect();
+
+    memory_draw_data.sort_by(|a,b|{
+        (a.1.layer).partial_cmp(&b.1.layer).unwrap()
+    });
+
+    //draw memories
+    for (pos,appearance) in memory_draw_data.iter() {
+        if let Some(tile) = con.get_mut(&on_screen(pos)) {
+            if let Some(current_fg) = tile.fg {
+                if !tile.ignore_overlap {
+                    tile.set_bg(current_fg);
+                }
+            }
+
+            tile.set_fg(appearance.color)
+                .set_c(appearance.sprite)
+                .set_ignore_overlap(appearance.ignore_overlap)
+                .set_layer(appearance.layer);
+        }
+    }
+
+    //draw entities in fov
+    for (pos,appearance) in draw_data.iter() {
+        if appearance.in_fov {
+            if let Some(tile) = con.get_mut(&on_screen(pos)) {
+                if let Some(current_fg) = tile.fg {
+                    if !tile.ignore_overlap {
+                        tile.set_bg(current_fg);
+                    }
+                }
+
+                tile.set_fg(appearance.color)
+                    .set_c(appearance.sprite)
+                    .set_ignore_overlap(appearance.ignore_overlap)
+                    .set_layer(appearance.layer);
+            }
+        }
+    }
+
+    //draw highlights
+    for (&(x,y),color) in resources.highlights.iter() {
+        if let Some(tile) = con.get_mut(&on_screen(&Pos::new(x,y))) {
+            tile.set_bg(*color);
+        }
+    }
+
+    //draw frame on a separate console
+    let mut con_with_frame = AsciiConsole::new(VIEW_WIDTH+2,VIEW_HEIGHT+2,None);
+    let mut set_frame = |pos,sprite:u32| {
+        if let Some(tile) = con_with_frame.get_mut(&pos) {
+            tile.set_fg(DARKGRAY);
+            tile.set_c(sprite);
+        }
+    };
+
+    set_frame((0,0),40);
+    set_frame((VIEW_WIDTH as i32+1,0),41);
+    set_frame((VIEW_WIDTH as i32+1,VIEW_HEIGHT as i32+1),42);
+    set_frame((0,VIEW_HEIGHT as i32+1),43);
+
+    for x in 1..=VIEW_WIDTH as i32 {
+        set_frame((x,0),44);
+        set_frame((x,VIEW_HEIGHT as i32+1),46);
+    }
+    for y in 1..=VIEW_HEIGHT as i32 {
+        set_frame((0,y),47);
+        set_frame((VIEW_WIDTH as i32+1,y),45);
+    }
+
+    //blit map onto frame console
+    con.blit(&(1,1),&mut con_with_frame);
+
+    con_with_frame
+}
+
+pub fn player(world: &World) -> Result<Entity,String> {
+    world.query::<&Player>().iter()
+        .next()
+        .map(|(ent,_)|ent)
+        .ok_or_else(||"No player found".to_owned())
+}
+
+//the map tile shown in the top left corner of the screen, the view is
+//kept centered on the player until it reaches the edge of the floor
+pub fn camera(world: &World, resources: &Resources) -> (i32,i32) {
+    let center: (i32,i32) = player(world).ok()
+        .and_then(|player|get_cloned::<Pos>(world,player))
+        .map(|pos|pos.into())
+        .unwrap_or((0,0));
+    let (width,height) = resources.spatial_index.size();
+    let axis = |center: i32, map: usize, view: usize| {
+        (center-view as i32/2).clamp(0,(map as i32-view as i32).max(0))
+    };
+    (axis(center.0,width,VIEW_WIDTH),axis(center.1,height,VIEW_HEIGHT))
+}
+
+//the top left corner of a map tile on screen, past the frame
+pub fn map_to_screen(camera: (i32,i32), tile: (i32,i32), tile_size: f32) -> (f32,f32) {
+    ((tile.0-camera.0+1) as f32*tile_size,(tile.1-camera.1+1) as f32*tile_size)
+}
+
+pub fn draw_map(world:&World,resources:&Resources) {
+    let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
+    let con = world_to_console(world,resources);
+    con.draw(
+        &resources.sprite_sheet,
+        &(SPRITE_WIDTH,SPRITE_HEIGHT),
+        SPRITE_SHEET_COLUMNS,
+        &(tile_size,tile_size),
+        &(0.,0.),
+    );
+}
+
+pub fn mouse_to_map(world: &World, resources: &Resources) -> Option<(i32,i32)> {
+    let abs_mouse_position = mouse_position();
+    let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
+    let tile_mouse_position = (
+        (abs_mouse_position.0 / tile_size).floor() as i32-1,
+        (abs_mouse_position.1 / tile_size).floor() as i32-1
+    );
+    
+    if tile_mouse_position.0 >= VIEW_WIDTH as i32 ||
+        tile_mouse_position.1 >= VIEW_HEIGHT as i32 {
+            return None;
+        }
+
+    if tile_mouse_position.0 < 0 ||
+        tile_mouse_position.1 < 0 {
+            return None;
+        }
+
+    let camera = camera(world,resources);
+    Some((tile_mouse_position.0+camera.0,tile_mouse_position.1+camera.1))
+}
+
+pub fn get_entities_at(resources: &Resources, pos: (i32,i32)) -> Vec<Entity> {
+    resources.spatial_index.at(pos).to_vec()
+}
+
+pub fn draw_map_and_hud(world: &World, resources: &Resources) {
+    clear_background(BLACK);
+    draw_map(world,resources);
+    draw_hud(world,resources);
+}
+
+pub fn clear_hightlights(resources:&mut Resources) {
+    resources.highlights.clear();
+}
+
+
+pub fn get_cloned<T: Clone + Component>(world: &World, entity: Entity) -> Option<T> {
+    if let Ok(component) = world.get::<T>(entity) {
+        Some((*component).clone())
+    } else {
+        None
+    }
+}
+
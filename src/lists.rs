@@ -1,109 +1,234 @@
-use crate::factory::EntityKind;
+use std::sync::RwLock;
 use lazy_static::lazy_static;
-use crate::dungeon_gen::DungeonTemplate;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use macroquad::rand::*;
+use crate::dungeon_gen::{DungeonTemplate,Generator,Vault,MIN_CELL_SIZE,MIN_GENERATED_SIZE};
+use crate::factory::template_exists;
 
+pub const DUNGEON_TEMPLATES_PATH: &str = "data/dungeon_templates.ron";
+pub const SPAWN_TABLES_PATH: &str = "data/spawn_tables.ron";
+pub const GENERATORS_PATH: &str = "data/generators.ron";
+pub const VAULTS_PATH: &str = "data/vaults.txt";
+
+#[derive(Clone,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnEntry {
+    pub template: String,
+    pub weight: i32,
+    #[serde(default)]
+    pub weight_per_floor: i32,
+    #[serde(default = "default_min_depth")]
+    pub min_depth: u32,
+    #[serde(default = "default_max_depth")]
+    pub max_depth: u32,
+}
+
+fn default_min_depth() -> u32 {
+    1
+}
+
+fn default_max_depth() -> u32 {
+    u32::MAX
+}
+
+impl SpawnEntry {
+    pub fn weight_at(&self, depth: u32) -> i32 {
+        depth_weight(self.weight,self.weight_per_floor,self.min_depth,self.max_depth,depth)
+    }
+}
+
+#[derive(Clone,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeneratorEntry {
+    pub generator: Generator,
+    pub weight: i32,
+    #[serde(default)]
+    pub weight_per_floor: i32,
+    #[serde(default = "default_min_depth")]
+    pub min_depth: u32,
+    #[serde(default = "default_max_depth")]
+    pub max_depth: u32,
+}
+
+impl GeneratorEntry {
+    pub fn weight_at(&self, depth: u32) -> i32 {
+        depth_weight(self.weight,self.weight_per_floor,self.min_depth,self.max_depth,depth)
+    }
+}
+
+fn depth_weight(weight: i32, weight_per_floor: i32, min_depth: u32, max_depth: u32, depth: u32) -> i32 {
+    if depth < min_depth || depth > max_depth {
+        return 0;
+    }
+    (weight + weight_per_floor * (depth as i32 - 1)).max(0)
+}
+
+#[derive(Clone,Default,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnTables {
+    pub enemies: Vec<SpawnEntry>,
+    pub items: Vec<SpawnEntry>,
+}
 
 lazy_static! {
-    pub static ref ENEMIES: Vec<EntityKind> = vec![
-        EntityKind::Bat,
-        EntityKind::Rat,
-        EntityKind::Centipede,
-        EntityKind::Zombie,
-        EntityKind::Gnome,
-        EntityKind::Goblin,
-        EntityKind::Wizard,
-    ];
-
-    pub static ref ITEMS: Vec<EntityKind> = vec![
-        EntityKind::HealthPotion,
-        EntityKind::Sword,
-        EntityKind::Axe,
-        EntityKind::Armor,
-        EntityKind::ThrowingSpear,
-        EntityKind::MagicMapping,
-    ];
-
-    pub static ref DUNGEON_TEMPLATES: Vec<DungeonTemplate> = vec![
-        DungeonTemplate {
-            entrance: 3,
-            exit: 4,
-            treasure_rooms: vec![2],
-            danger_rooms: vec![8],
-            doors: [
-              1,1,
-             1,0,1,
-              0,1,
-             1,0,1,
-              1,1,
-            ],
-        },
-        DungeonTemplate {
-            entrance: 0,
-            exit: 7,
-            treasure_rooms: vec![8],
-            danger_rooms: vec![8],
-            doors: [
-              1,1,
-             0,0,1,
-              1,1,
-             1,0,0,
-              1,1,
-            ],
-        },
-        DungeonTemplate {
-            entrance: 4,
-            exit: 0,
-            treasure_rooms: vec![8],
-            danger_rooms: vec![],
-            doors: [
-              1,1,
-             0,1,1,
-              1,1,
-             0,1,0,
-              1,1,
-            ],
-        },
-        DungeonTemplate {
-            entrance: 4,
-            exit: 5,
-            treasure_rooms: vec![7],
-            danger_rooms: vec![8],
-            doors: [
-              1,1,
-             1,0,1,
-              1,0,
-             1,0,0,
-              1,1,
-            ],
-        },
-        DungeonTemplate {
-            entrance: 4,
-            exit: 5,
-            treasure_rooms: vec![2],
-            danger_rooms: vec![6],
-            doors: [
-              0,1,
-             1,1,0,
-              1,0,
-             1,1,1,
-              0,1,
-            ],
-        },
-        DungeonTemplate {
-            entrance: 0,
-            exit: 2,
-            treasure_rooms: vec![8],
-            danger_rooms: vec![7],
-            doors: [
-              0,0,
-             1,1,1,
-              1,1,
-             1,1,1,
-              1,0,
-            ],
-        },
-
-
-    ];
+    static ref DUNGEON_TEMPLATES: RwLock<Vec<DungeonTemplate>> = RwLock::new(Vec::new());
+    static ref SPAWN_TABLES: RwLock<SpawnTables> = RwLock::new(SpawnTables::default());
+    static ref GENERATORS: RwLock<Vec<GeneratorEntry>> = RwLock::new(Vec::new());
+    static ref VAULTS: RwLock<Vec<Vault>> = RwLock::new(Vec::new());
+}
+
+//parses one of the files in assets/data, errors name the file and line
+pub fn parse_data<T: DeserializeOwned>(source: &str, file_name: &str) -> Result<T,String> {
+    use ron::extensions::Extensions;
+    ron::Options::default()
+        .with_default_extension(Extensions::IMPLICIT_SOME)
+        .from_str(source)
+        .map_err(|e|format!("{}:{}:{}: {}",file_name,e.position.line,e.position.col,e.code))
+}
+
+pub fn load_dungeon_templates(source: &str, file_name: &str) -> Result<(),String> {
+    let templates: Vec<DungeonTemplate> = parse_data(source,file_name)?;
+    if templates.is_empty() {
+        return Err(format!("{}: needs at least one dungeon template",file_name));
+    }
+    for (i,template) in templates.iter().enumerate() {
+        let rooms = [template.entrance,template.exit].iter()
+            .chain(template.treasure_rooms.iter())
+            .chain(template.danger_rooms.iter())
+            .all(|room|*room < 9);
+        if !rooms {
+            return Err(format!("{}: template {} uses a room outside of 0-8",file_name,i));
+        }
+        if template.cell_size < MIN_CELL_SIZE {
+            return Err(format!("{}: template {} has a cell_size below {}",file_name,i,MIN_CELL_SIZE));
+        }
+        let unreachable = template.unreachable_rooms();
+        let cut_off = std::iter::once(&template.exit)
+            .chain(template.treasure_rooms.iter())
+            .any(|room|unreachable.contains(room));
+        if cut_off {
+            return Err(format!("{}: template {} has no doors to its exit or treasure from the entrance",file_name,i));
+        }
+    }
+    *DUNGEON_TEMPLATES.write().unwrap() = templates;
+    Ok(())
+}
+
+//entity templates have to be loaded first, so the tables can be checked against them
+pub fn load_spawn_tables(source: &str, file_name: &str) -> Result<(),String> {
+    let tables: SpawnTables = parse_data(source,file_name)?;
+    for entry in tables.enemies.iter().chain(tables.items.iter()) {
+        if !template_exists(&entry.template) {
+            return Err(format!("{}: `{}` is not an entity template",file_name,entry.template));
+        }
+        if entry.min_depth > entry.max_depth {
+            return Err(format!("{}: `{}` has a min_depth above its max_depth",file_name,entry.template));
+        }
+    }
+    *SPAWN_TABLES.write().unwrap() = tables;
+    Ok(())
+}
+
+pub fn load_generators(source: &str, file_name: &str) -> Result<(),String> {
+    let entries: Vec<GeneratorEntry> = parse_data(source,file_name)?;
+    for (i,entry) in entries.iter().enumerate() {
+        match entry.generator {
+            Generator::Caves{width,height} | Generator::Bsp{width,height}
+                if width < MIN_GENERATED_SIZE || height < MIN_GENERATED_SIZE => {
+                    return Err(format!("{}: entry {} is smaller than {}x{}",file_name,i,MIN_GENERATED_SIZE,MIN_GENERATED_SIZE));
+                }
+            _ => {}
+        }
+        if entry.min_depth > entry.max_depth {
+            return Err(format!("{}: entry {} has a min_depth above its max_depth",file_name,i));
+        }
+    }
+    *GENERATORS.write().unwrap() = entries;
+    Ok(())
+}
+
+//vaults are drawn one after another with a blank line between them, lines
+//starting with // are comments
+pub fn load_vaults(source: &str, file_name: &str) -> Result<(),String> {
+    let mut vaults = Vec::new();
+    let mut rows: Vec<(usize,&str)> = Vec::new();
+    let lines = source.lines().enumerate()
+        .filter(|(_,line)|!line.starts_with("//"))
+        .map(|(i,line)|(i+1,line.trim_end()))
+        .chain(std::iter::once((0,"")));
+    for (line_number,line) in lines {
+        if !line.is_empty() {
+            rows.push((line_number,line));
+            continue;
+        }
+        if rows.is_empty() {
+            continue;
+        }
+        let width = rows[0].1.chars().count();
+        let mut tiles = Vec::new();
+        for (line_number,row) in rows.iter() {
+            if row.chars().count() != width {
+                return Err(format!("{}:{}: every row of a vault has to be the same width",file_name,line_number));
+            }
+            if let Some(c) = row.chars().find(|c|!"#.+m*".contains(*c)) {
+                return Err(format!("{}:{}: `{}` is not a vault tile",file_name,line_number,c));
+            }
+            tiles.extend(row.chars());
+        }
+        vaults.push(Vault{width,height: rows.len(),tiles});
+        rows.clear();
+    }
+    *VAULTS.write().unwrap() = vaults;
+    Ok(())
+}
+
+pub fn choose_dungeon_template() -> Option<DungeonTemplate> {
+    DUNGEON_TEMPLATES.read().unwrap().choose().cloned()
+}
+
+pub fn dungeon_templates() -> Vec<DungeonTemplate> {
+    DUNGEON_TEMPLATES.read().unwrap().clone()
+}
+
+pub fn choose_enemy(depth: u32) -> Option<String> {
+    choose_weighted(&SPAWN_TABLES.read().unwrap().enemies,|e|e.weight_at(depth))
+        .map(|e|e.template.clone())
+}
+
+pub fn choose_item(depth: u32) -> Option<String> {
+    choose_weighted(&SPAWN_TABLES.read().unwrap().items,|e|e.weight_at(depth))
+        .map(|e|e.template.clone())
+}
+
+pub fn choose_generator(depth: u32) -> Option<Generator> {
+    let entries = GENERATORS.read().unwrap();
+    choose_weighted(&entries,|e|e.weight_at(depth)).map(|e|e.generator.clone())
+}
+
+//a vault that fits in the given space
+pub fn choose_vault(max_width: usize, max_height: usize) -> Option<Vault> {
+    let fitting: Vec<Vault> = VAULTS.read().unwrap().iter()
+        .filter(|vault|vault.width <= max_width && vault.height <= max_height)
+        .cloned()
+        .collect();
+    fitting.choose().cloned()
+}
 
+fn choose_weighted<T>(entries: &[T], weight_at: impl Fn(&T)->i32) -> Option<&T> {
+    let total: i32 = entries.iter().map(&weight_at).sum();
+    if total <= 0 {
+        return None;
+    }
+    let mut roll = gen_range(0,total);
+    for entry in entries {
+        let weight = weight_at(entry);
+        if roll < weight {
+            return Some(entry);
+        }
+        roll -= weight;
+    }
+    //rounding in gen_range can land on the very top of the range
+    entries.iter().rev()
+        .find(|e|weight_at(e) > 0)
 }
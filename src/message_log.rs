@@ -0,0 +1,82 @@
+use hecs::*;
+use macroquad::color::*;
+use serde::{Serialize,Deserialize};
+use crate::prelude::*;
+
+const MAX_LOG_LINES: usize = 200;
+
+//everything that happened during the run, unlike screen messages these
+//don't fade so the player can look back through them
+#[derive(Clone,Default,Serialize,Deserialize)]
+pub struct MessageLog {
+    pub lines: Vec<LogLine>,
+    //how many lines back from the newest the player has scrolled
+    #[serde(skip)]
+    pub scroll: usize,
+}
+
+#[derive(Clone,Serialize,Deserialize)]
+pub struct LogLine {
+    pub text: String,
+    #[serde(with = "color_serde")]
+    pub color: Color,
+    //repeats of the same line are counted instead of added again
+    pub count: u32,
+}
+
+impl LogLine {
+    pub fn display(&self) -> String {
+        if self.count > 1 {
+            format!("{} (x{})",self.text,self.count)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+impl MessageLog {
+    pub fn push(&mut self, text: String, color: Color) {
+        self.scroll = 0;
+        if let Some(last) = self.lines.last_mut() {
+            if last.text == text {
+                last.count += 1;
+                return;
+            }
+        }
+        self.lines.push(LogLine{
+            text,
+            color,
+            count: 1,
+        });
+        if self.lines.len() > MAX_LOG_LINES {
+            self.lines.remove(0);
+        }
+    }
+
+    pub fn scroll_by(&mut self, lines: i32) {
+        let max = self.lines.len().saturating_sub(1) as i32;
+        self.scroll = (self.scroll as i32 + lines).clamp(0,max.max(0)) as usize;
+    }
+}
+
+pub fn log(resources: &mut Resources, text: String, color: Color) {
+    resources.message_log.push(text,color);
+}
+
+//"you" for the player, otherwise the entity's name
+pub fn log_name(world: &World, ent: Entity) -> String {
+    if world.get::<Player>(ent).is_ok() {
+        return "you".to_owned();
+    }
+    get_cloned::<Name>(world,ent)
+        .map(|name|name.name)
+        .unwrap_or_else(||"something".to_owned())
+}
+
+pub fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -2,17 +2,24 @@
 use hecs::*;
 use crate::prelude::*;
 
-pub fn memory_system(world: &mut World,_resources: &mut Resources) {
+pub fn memory_system(world: &mut World,resources: &mut Resources) {
     for (_,monster_memory) in world.query::<&mut MonsterMemory>().iter(){
         if monster_memory.time_to_remember > 0 {
             monster_memory.time_to_remember -=1;
         }
     }
-    for (_,(appearance,monster_memory)) in 
-        world.query::<(&Appearance,&mut MonsterMemory)>().iter() {
-            if appearance.in_fov {
-                monster_memory.time_to_remember = monster_memory.strength;
-            }
-
+    let player = match player(world) {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    //monsters remember the player for as long as they can see them
+    let seeing: Vec<Entity> = world.query::<&MonsterMemory>().iter()
+        .map(|(ent,_)|ent)
+        .filter(|ent|can_see(world,resources,*ent,player))
+        .collect();
+    for ent in seeing {
+        if let Ok(mut monster_memory) = world.get_mut::<MonsterMemory>(ent) {
+            monster_memory.time_to_remember = monster_memory.strength;
+        }
     }
 }
@@ -1,24 +1,59 @@
 
-use std::collections::{HashMap,HashSet};
 use macroquad::prelude::*;
 use hecs::*;
 use crate::prelude::*;
+use serde::{Serialize,Deserialize};
 
-#[derive(Copy,Clone)]
+#[derive(Copy,Clone,Debug,PartialEq,Serialize,Deserialize)]
 pub enum Dir {
-    N,W,S,E
+    N,W,S,E,
+    NE,NW,SE,SW,
 }
 
 impl Dir {
+    pub const CARDINAL: [Dir;4] = [Dir::N,Dir::W,Dir::S,Dir::E];
+    pub const ALL: [Dir;8] = [Dir::N,Dir::W,Dir::S,Dir::E,Dir::NE,Dir::NW,Dir::SE,Dir::SW];
+
+    //the directions something can step in with or without diagonals
+    pub fn directions(diagonals: bool) -> &'static [Dir] {
+        if diagonals {
+            &Dir::ALL
+        } else {
+            &Dir::CARDINAL
+        }
+    }
+
     pub fn components(&self) -> (i32,i32) {
         match self {
             Dir::N => (0,-1),
             Dir::W => (-1,0),
             Dir::S => (0,1),
             Dir::E => (1,0),
-
+            Dir::NE => (1,-1),
+            Dir::NW => (-1,-1),
+            Dir::SE => (1,1),
+            Dir::SW => (-1,1),
         }
     }
+
+    pub fn from_components(components: (i32,i32)) -> Option<Dir> {
+        Dir::ALL.iter().copied().find(|dir|dir.components() == components)
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        let (dx,dy) = self.components();
+        dx != 0 && dy != 0
+    }
+}
+
+//the tiles a step away from tile
+pub fn neighbours(tile: (i32,i32), diagonals: bool) -> Vec<(i32,i32)> {
+    Dir::directions(diagonals).iter()
+        .map(|dir|{
+            let (dx,dy) = dir.components();
+            (tile.0+dx,tile.1+dy)
+        })
+        .collect()
 }
 
 pub fn distance_between_ents(world:&World,start:Entity,finish:Entity) -> Option<i32> {
@@ -29,82 +64,57 @@ pub fn distance_between_ents(world:&World,start:Entity,finish:Entity) -> Option<
     Some(absdiff(start_pos.x,finish_pos.y) + absdiff(start_pos.y,finish_pos.y))
 }
 
-pub fn movement_map(world: &World) -> HashMap<(i32,i32),(Entity,Bump)> {
-    let mut map : HashMap<(i32,i32),(Entity,Bump)> = HashMap::new();
-
-    for (entity,(
-        pos,
-        bump
-    )) in world.query::<(
-        &Pos,
-        &Bump
-    )>().iter() {
-        map.insert((*pos).into(),(entity,bump.clone()));
-    }
-    map
+//what walking into the tile would bump into, if anything
+pub fn bump_at(world: &World, resources: &Resources, tile: (i32,i32)) -> Option<(Entity,Bump)> {
+    resources.spatial_index.at(tile).iter()
+        .find_map(|ent|get_cloned::<Bump>(world,*ent).map(|bump|(*ent,bump)))
 }
 
-pub fn stepping_tiles(world: &World) -> HashSet<(i32,i32)> {
-    let mut map : HashSet<(i32,i32)> = HashSet::new();
-
-    for (_,(
-        pos,
-        _
-    )) in world.query::<(
-        &Pos,
-        &Bump
-    )>().iter() {
-        map.insert((*pos).into());
-    }
+//whether something on the tile keeps anything else from stepping there
+pub fn is_blocked(world: &World, resources: &Resources, tile: (i32,i32)) -> bool {
+    resources.spatial_index.at(tile).iter()
+        .any(|ent|world.get::<Bump>(*ent).is_ok() || world.get::<Player>(*ent).is_ok())
+}
 
-    for (_,(
-        pos,
-        _
-    )) in world.query::<(
-        &Pos,
-        &Player
-    )>().iter() {
-        map.insert((*pos).into());
-    }
-    map
+//every tile on the level that can be stood on right now, sorted so that
+//picking from it with the seeded rng stays deterministic
+pub fn walkable_tiles(world: &World, resources: &Resources) -> Vec<(i32,i32)> {
+    let index = &resources.spatial_index;
+    index.tiles()
+        .filter(|tile|{
+            index.at(*tile).iter().any(|ent|{
+                world.get::<OnLevel>(*ent).is_ok() && world.get::<PlayerMemory>(*ent).is_err()
+            })
+        })
+        .filter(|tile|!is_blocked(world,resources,*tile))
+        .collect()
 }
 
 pub mod my_pathfinding {
-    use pathfinding::prelude::{absdiff,astar};
+    use pathfinding::prelude::{absdiff,astar,bfs};
     use crate::components::Pos;
+    use crate::resources::Resources;
     use hecs::*;
-    use crate::movement::stepping_tiles;
+    use crate::components::Bump;
+    use crate::movement::{is_blocked,bump_at,neighbours,walkable_tiles};
+    use crate::get_cloned;
     use std::collections::HashSet;
 
-    pub fn basic_path(world: &World, actor:Entity, target:Entity) -> Option<(Vec<(i32,i32)>,u32)> {
-        let mut actor_pos = None;
-        let mut target_pos = None;
-        for (ent,pos) in world.query::<&Pos>().iter() {
-            if ent == actor {
-                actor_pos.replace((pos.x,pos.y));
-            }
-            if ent == target {
-                target_pos.replace((pos.x,pos.y));
-            }
-        }
-
-        let m_map = stepping_tiles(world);
-
-        let actor_pos = actor_pos?;
-        let target_pos = target_pos?;
+    pub fn basic_path(world: &World, resources: &Resources, actor:Entity, target:Entity, diagonals: bool) -> Option<(Vec<(i32,i32)>,u32)> {
+        let actor_pos = get_cloned::<Pos>(world,actor)?;
+        let target_pos = get_cloned::<Pos>(world,target)?;
 
-        path(m_map,actor_pos,target_pos)
+        path(world,resources,actor_pos.into(),target_pos.into(),diagonals)
     }
 
+    //the start and goal count as open even if something stands on them
     pub fn path(
-        mut move_map: HashSet<(i32,i32)>, 
+        world: &World,
+        resources: &Resources,
         start: (i32,i32), 
-        goal: (i32,i32)
+        goal: (i32,i32),
+        diagonals: bool,
     ) -> Option<(Vec<(i32,i32)>,u32)>{
-        move_map.remove(&start);
-        move_map.remove(&goal);
-        
-
         //allows for the player to move in a 
         let tall = {
             let dx = absdiff(start.0,goal.0);
@@ -115,21 +125,29 @@ pub mod my_pathfinding {
 
         let successors= |pos: &(i32,i32)| -> Vec<((i32,i32),u32)> {
             let &(x,y) = pos;
-            let vec = if tall {
+            let mut vec = if tall {
                 vec![(x,y-1),(x,y+1),(x-1,y),(x+1,y)]
             } else {
                 vec![(x-1,y),(x+1,y),(x,y-1),(x,y+1)]
             };
+            //diagonal steps come last so straight lines are still preferred
+            if diagonals {
+                vec.extend(neighbours(*pos,true).into_iter().filter(|p|p.0 != x && p.1 != y));
+            }
             vec
                 .into_iter()
-                .filter(|p|{!move_map.contains(&p)})
+                .filter(|p|*p == start || *p == goal || !is_blocked(world,resources,*p))
                 .map(|p|(p,1))
                 .collect()
 
         };
 
         let distance = |pos: &(i32,i32)| -> u32 {
-            (absdiff(pos.0,goal.0) + absdiff(pos.1,goal.1)) as u32
+            if diagonals {
+                absdiff(pos.0,goal.0).max(absdiff(pos.1,goal.1)) as u32
+            } else {
+                (absdiff(pos.0,goal.0) + absdiff(pos.1,goal.1)) as u32
+            }
         };
 
         let success = |pos: &(i32,i32)| -> bool {
@@ -138,4 +156,23 @@ pub mod my_pathfinding {
 
         astar(&start,successors,distance,success)
     }
+
+    //the shortest walk to a tile that isn't in explored, only crossing
+    //tiles that can be stood on or doors that walking into will open
+    pub fn unexplored_path(
+        world: &World,
+        resources: &Resources,
+        start: (i32,i32),
+        explored: &HashSet<(i32,i32)>,
+        diagonals: bool,
+    ) -> Option<Vec<(i32,i32)>> {
+        let walkable: HashSet<(i32,i32)> = walkable_tiles(world,resources).into_iter().collect();
+        bfs(
+            &start,
+            |pos| neighbours(*pos,diagonals).into_iter()
+                .filter(|p|walkable.contains(p) || matches!(bump_at(world,resources,*p),Some((_,Bump::OpenDoor))))
+                .collect::<Vec<(i32,i32)>>(),
+            |pos| *pos != start && !explored.contains(pos),
+        )
+    }
 }
@@ -0,0 +1,93 @@
+use hecs::*;
+use serde::{Serialize,Deserialize};
+use crate::prelude::*;
+use crate::simulation::*;
+use crate::save::reload;
+
+pub const REPLAY_PATH: &str = "replay.ron";
+
+//a run is fully determined by its seed and the actions the player took,
+//so that is all a replay has to store
+#[derive(Clone,Serialize,Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    //replays from before diagonal movement are 4-way
+    #[serde(default)]
+    pub diagonals: bool,
+    pub turns: Vec<Vec<PlayerAction>>,
+    //the turns a saved run was continued at and what the rng was seeded
+    //with there, since a save can't carry the rng along with it
+    #[serde(default)]
+    pub reseeds: Vec<(usize,u64)>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, diagonals: bool) -> Self {
+        Self {
+            seed,
+            diagonals,
+            turns: Vec::new(),
+            reseeds: Vec::new(),
+        }
+    }
+}
+
+pub fn replay_exists() -> bool {
+    std::path::Path::new(REPLAY_PATH).exists()
+}
+
+pub fn save_replay(replay: &Replay, path: &str) -> Result<(),String> {
+    let text = ron::ser::to_string_pretty(replay,ron::ser::PrettyConfig::default())
+        .map_err(|e|format!("could not serialize replay: {}",e))?;
+    std::fs::write(path,text)
+        .map_err(|e|format!("could not write {}: {}",path,e))
+}
+
+pub fn load_replay(path: &str) -> Result<Replay,String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e|format!("could not read {}: {}",path,e))?;
+    ron::from_str(&text)
+        .map_err(|e|format!("{}: {}",path,e))
+}
+
+//starts the replay's run, without recording it again
+pub fn start_replay(world: &mut World, resources: &mut Resources, replay: &Replay) {
+    resources.settings.diagonals = replay.diagonals;
+    new_game(world,resources,replay.seed);
+    resources.replay = None;
+}
+
+//seeds the rng for a run continued from a save, from the run's seed and
+//how far into it the save was, and notes it so playback can do the same
+pub fn resume_rng(resources: &mut Resources) {
+    let seed = resources.seed
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(resources.time);
+    macroquad::rand::srand(seed);
+    if let Some(replay) = &mut resources.replay {
+        replay.reseeds.push((replay.turns.len(),seed));
+    }
+}
+
+//plays one recorded turn, first reloading the world and reseeding if the
+//run was continued from a save there
+pub fn play_turn(world: &mut World, resources: &mut Resources, replay: &Replay, turn: usize) {
+    for (_,seed) in replay.reseeds.iter().filter(|(at,_)|*at == turn) {
+        reload(world,resources).ok();
+        macroquad::rand::srand(*seed);
+    }
+    take_turn(world,resources,replay.turns[turn].clone());
+}
+
+//plays a whole replay without drawing anything and reports how the run ended
+pub fn run_replay(world: &mut World, resources: &mut Resources, replay: &Replay) -> RunStatus {
+    start_replay(world,resources,replay);
+    for turn in 0..replay.turns.len() {
+        play_turn(world,resources,replay,turn);
+        match run_status(world,resources) {
+            RunStatus::Playing => {}
+            status => {return status;}
+        }
+    }
+    run_status(world,resources)
+}
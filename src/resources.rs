@@ -1,8 +1,14 @@
-use std::collections::HashSet;
+use std::collections::{HashMap,HashSet};
 use macroquad::prelude::*;
 use hecs::*;
-use crate::constants::*;
 use macroquad::ui::*;
+use crate::screen_messages::{ScreenMessage,RangedAttackAnimation,BlastAnimation};
+use crate::replay::Replay;
+use crate::message_log::MessageLog;
+use crate::settings::{Settings,load_settings};
+use crate::factory::{load_templates,ENTITY_TEMPLATES_PATH};
+use crate::lists::*;
+use crate::spatial_index::SpatialIndex;
 
 pub struct Resources{
     pub ascii: Texture2D,
@@ -10,9 +16,49 @@ pub struct Resources{
     pub font: Font,
     pub player: PlayerData,
     pub fov_set: HashSet<(i32,i32)>,
-    pub highlights: Vec<Option<Color>>,
+    pub highlights: HashMap<(i32,i32),Color>,
     pub new_level_request: bool,
     pub level: u32,
+    pub seed: u64,
+    //ticks since the run started
+    pub time: u64,
+    pub screen_messages: Vec<ScreenMessage>,
+    pub animations: Vec<RangedAttackAnimation>,
+    pub blasts: Vec<BlastAnimation>,
+    pub message_log: MessageLog,
+    pub replay: Option<Replay>,
+    pub settings: Settings,
+    pub spatial_index: SpatialIndex,
+}
+
+impl Resources {
+    pub fn new(ascii: Texture2D, sprite_sheet: Texture2D, font: Font) -> Self {
+        Self {
+            ascii,
+            sprite_sheet,
+            font,
+            player: PlayerData::new(),
+            fov_set: HashSet::new(),
+            highlights: HashMap::new(),
+            new_level_request: false,
+            level: 0,
+            seed: 0,
+            time: 0,
+            screen_messages: Vec::new(),
+            animations: Vec::new(),
+            blasts: Vec::new(),
+            message_log: MessageLog::default(),
+            replay: None,
+            settings: Settings::default(),
+            spatial_index: SpatialIndex::new(0,0),
+        }
+    }
+
+    //resources without any textures or fonts, for running the game
+    //without a window
+    pub fn headless() -> Self {
+        Self::new(Texture2D::empty(),Texture2D::empty(),Font::default())
+    }
 }
 
 pub async fn load_resources() -> Resources {
@@ -49,21 +95,58 @@ pub async fn load_resources() -> Resources {
 
     root_ui().push_skin(&skin);
 
+    if let Err(e) = load_data().await {
+        panic!("{}",e);
+    }
 
 
-    Resources {
-        ascii: load_and_filter("textures/ascii_8x8.png").await,
-        sprite_sheet: load_and_filter("textures/sprite-sheet.png").await,
-        font: load_ttf_font("fonts/FiraMono-Medium.ttf").await.unwrap(),
-        player: PlayerData::new(),
-        fov_set: HashSet::new(),
-        highlights: vec![None;ARENA_WIDTH*ARENA_HEIGHT],
-        new_level_request: false,
-        level: 0,
-    }
+    let mut resources = Resources::new(
+        load_and_filter("textures/ascii_8x8.png").await,
+        load_and_filter("textures/sprite-sheet.png").await,
+        load_ttf_font("fonts/FiraMono-Medium.ttf").await.unwrap(),
+    );
+    resources.settings = load_settings();
+    resources
 }
 
 
+//game data is reloaded every time the main menu opens, so edits to the
+//files in assets/data show up without restarting
+async fn load_data() -> Result<(),String> {
+    let read = |path: &'static str| async move {
+        load_string(path).await
+            .map_err(|e|format!("could not read {}: {}",path,e))
+    };
+    load_templates(&read(ENTITY_TEMPLATES_PATH).await?,ENTITY_TEMPLATES_PATH)?;
+    load_dungeon_templates(&read(DUNGEON_TEMPLATES_PATH).await?,DUNGEON_TEMPLATES_PATH)?;
+    load_spawn_tables(&read(SPAWN_TABLES_PATH).await?,SPAWN_TABLES_PATH)?;
+    load_generators(&read(GENERATORS_PATH).await?,GENERATORS_PATH)?;
+    load_vaults(&read(VAULTS_PATH).await?,VAULTS_PATH)?;
+    Ok(())
+}
+
+//loads the same data as load_resources straight from disk, for running
+//without a window
+pub fn load_data_from_dir(assets_dir: &str) -> Result<(),String> {
+    let read = |path: &str| {
+        let path = format!("{}/{}",assets_dir,path);
+        std::fs::read_to_string(&path)
+            .map(|source|(source,path.clone()))
+            .map_err(|e|format!("could not read {}: {}",path,e))
+    };
+    let (source,path) = read(ENTITY_TEMPLATES_PATH)?;
+    load_templates(&source,&path)?;
+    let (source,path) = read(DUNGEON_TEMPLATES_PATH)?;
+    load_dungeon_templates(&source,&path)?;
+    let (source,path) = read(SPAWN_TABLES_PATH)?;
+    load_spawn_tables(&source,&path)?;
+    let (source,path) = read(GENERATORS_PATH)?;
+    load_generators(&source,&path)?;
+    let (source,path) = read(VAULTS_PATH)?;
+    load_vaults(&source,&path)?;
+    Ok(())
+}
+
 async fn load_and_filter(path: &str) -> Texture2D {
     let texture = load_texture(path).await.unwrap();
     texture.set_filter(FilterMode::Nearest);
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use hecs::*;
+use serde::{Serialize,Deserialize};
+use crate::prelude::*;
+use crate::resources::PlayerData;
+use crate::dungeon_gen::{map_size,MIN_CELL_SIZE};
+use crate::replay::{Replay,resume_rng};
+
+const SAVE_PATH: &str = "save.ron";
+
+#[derive(Serialize,Deserialize)]
+struct SaveData {
+    level: u32,
+    #[serde(default)]
+    seed: u64,
+    inventory: Vec<Entity>,
+    entities: Vec<SavedEntity>,
+    #[serde(default)]
+    message_log: MessageLog,
+    #[serde(default)]
+    time: u64,
+    //saves from before floors could change size are all the smallest size
+    #[serde(default="default_map_size")]
+    map_size: (usize,usize),
+    //the run so far, so a continued run can still be replayed
+    #[serde(default)]
+    replay: Option<Replay>,
+}
+
+fn default_map_size() -> (usize,usize) {
+    let size = map_size(MIN_CELL_SIZE);
+    (size,size)
+}
+
+//every component that makes up a run in progress, entity references still
+//point at the ids from the world that was saved
+#[derive(Serialize,Deserialize)]
+struct SavedEntity {
+    id: Entity,
+    #[serde(default, skip_serializing_if = "Option::is_none")] ammo: Option<Ammo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] appearance: Option<Appearance>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] armor: Option<Armor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] behavior: Option<Behavior>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] blocks_sight: Option<BlocksSight>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] bump: Option<Bump>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] defense: Option<Defense>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] energy: Option<Energy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] equipment: Option<Equipment>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] equipable: Option<Equipable>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] health: Option<Health>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] item: Option<Item>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] light: Option<Light>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] on_level: Option<OnLevel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] monster_memory: Option<MonsterMemory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] name: Option<Name>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] player_memory: Option<PlayerMemory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] player: Option<Player>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pos: Option<Pos>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] stack: Option<Stack>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] status_effects: Option<StatusEffects>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] useable: Option<Useable>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] weapon: Option<Weapon>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] weight: Option<Weight>,
+}
+
+impl SavedEntity {
+    fn capture(world: &World, id: Entity) -> Self {
+        Self {
+            id,
+            ammo: get_cloned(world,id),
+            appearance: get_cloned(world,id),
+            armor: get_cloned(world,id),
+            behavior: get_cloned(world,id),
+            blocks_sight: get_cloned(world,id),
+            bump: get_cloned(world,id),
+            defense: get_cloned(world,id),
+            energy: get_cloned(world,id),
+            equipment: get_cloned(world,id),
+            equipable: get_cloned(world,id),
+            health: get_cloned(world,id),
+            item: get_cloned(world,id),
+            light: get_cloned(world,id),
+            on_level: get_cloned(world,id),
+            monster_memory: get_cloned(world,id),
+            name: get_cloned(world,id),
+            player_memory: get_cloned(world,id),
+            player: get_cloned(world,id),
+            pos: get_cloned(world,id),
+            stack: get_cloned(world,id),
+            status_effects: get_cloned(world,id),
+            useable: get_cloned(world,id),
+            weapon: get_cloned(world,id),
+            weight: get_cloned(world,id),
+        }
+    }
+
+    //entities like screen messages and animations aren't part of the run
+    fn is_empty(&self) -> bool {
+        self.appearance.is_none() &&
+            self.item.is_none() &&
+            self.player.is_none() &&
+            self.pos.is_none()
+    }
+
+    fn restore(self, world: &mut World) -> Entity {
+        let mut builder = EntityBuilder::new();
+        if let Some(c) = self.ammo { builder.add(c); }
+        if let Some(c) = self.appearance { builder.add(c); }
+        if let Some(c) = self.armor { builder.add(c); }
+        if let Some(c) = self.behavior { builder.add(c); }
+        if let Some(c) = self.blocks_sight { builder.add(c); }
+        if let Some(c) = self.bump { builder.add(c); }
+        if let Some(c) = self.defense { builder.add(c); }
+        if let Some(c) = self.energy { builder.add(c); }
+        if let Some(c) = self.equipment { builder.add(c); }
+        if let Some(c) = self.equipable { builder.add(c); }
+        if let Some(c) = self.health { builder.add(c); }
+        if let Some(c) = self.item { builder.add(c); }
+        if let Some(c) = self.light { builder.add(c); }
+        if let Some(c) = self.on_level { builder.add(c); }
+        if let Some(c) = self.monster_memory { builder.add(c); }
+        if let Some(c) = self.name { builder.add(c); }
+        if let Some(c) = self.player_memory { builder.add(c); }
+        if let Some(c) = self.player { builder.add(c); }
+        if let Some(c) = self.pos { builder.add(c); }
+        if let Some(c) = self.stack { builder.add(c); }
+        if let Some(c) = self.status_effects { builder.add(c); }
+        if let Some(c) = self.useable { builder.add(c); }
+        if let Some(c) = self.weapon { builder.add(c); }
+        if let Some(c) = self.weight { builder.add(c); }
+        world.spawn(builder.build())
+    }
+}
+
+pub fn save_exists() -> bool {
+    std::path::Path::new(SAVE_PATH).exists()
+}
+
+pub fn delete_save() {
+    std::fs::remove_file(SAVE_PATH).ok();
+}
+
+pub fn save_game(world: &World, resources: &Resources) -> Result<(),String> {
+    let text = save_to_string(world,resources)?;
+    std::fs::write(SAVE_PATH,text)
+        .map_err(|e|format!("could not write {}: {}",SAVE_PATH,e))
+}
+
+pub fn save_to_string(world: &World, resources: &Resources) -> Result<String,String> {
+    let entities: Vec<SavedEntity> = world.iter()
+        .map(|entity_ref|SavedEntity::capture(world,entity_ref.entity()))
+        .filter(|saved|!saved.is_empty())
+        .collect();
+
+    let data = SaveData {
+        level: resources.level,
+        seed: resources.seed,
+        inventory: resources.player.inventory.clone(),
+        entities,
+        message_log: resources.message_log.clone(),
+        time: resources.time,
+        map_size: resources.spatial_index.size(),
+        replay: resources.replay.clone(),
+    };
+
+    ron::ser::to_string_pretty(&data,ron::ser::PrettyConfig::default())
+        .map_err(|e|format!("could not serialize save: {}",e))
+}
+
+pub fn load_game(world: &mut World, resources: &mut Resources) -> Result<(),String> {
+    let text = std::fs::read_to_string(SAVE_PATH)
+        .map_err(|e|format!("could not read {}: {}",SAVE_PATH,e))?;
+    load_from_str(world,resources,&text)
+        .map_err(|e|format!("{}: {}",SAVE_PATH,e))?;
+    resume_rng(resources);
+    Ok(())
+}
+
+//saves and loads the run straight back, the way continuing a run does,
+//so a replay can go through the same reload the run it recorded did
+pub fn reload(world: &mut World, resources: &mut Resources) -> Result<(),String> {
+    let text = save_to_string(world,resources)?;
+    load_from_str(world,resources,&text)
+}
+
+pub fn load_from_str(world: &mut World, resources: &mut Resources, text: &str) -> Result<(),String> {
+    let data: SaveData = ron::from_str(text)
+        .map_err(|e|e.to_string())?;
+
+    //a fresh world rather than a cleared one, so the restored entities get
+    //the same ids whatever world was there before
+    *world = World::new();
+
+    //entities get new ids when respawned, so remember where each old id went
+    let mut new_ids: HashMap<Entity,Entity> = HashMap::new();
+    for saved in data.entities {
+        let old_id = saved.id;
+        let new_id = saved.restore(world);
+        new_ids.insert(old_id,new_id);
+    }
+
+    let remap = |ent: Entity| -> Result<Entity,String> {
+        new_ids.get(&ent).copied()
+            .ok_or_else(||format!("save refers to missing entity {:?}",ent))
+    };
+
+    for (_,equipment) in world.query::<&mut Equipment>().iter() {
+        equipment.weapon = equipment.weapon.map(remap).transpose()?;
+        equipment.armor = equipment.armor.map(remap).transpose()?;
+    }
+
+    let mut player_data = PlayerData::new();
+    for ent in data.inventory {
+        player_data.inventory.push(remap(ent)?);
+    }
+
+    resources.player = player_data;
+    resources.level = data.level;
+    resources.seed = data.seed;
+    resources.message_log = data.message_log;
+    resources.time = data.time;
+    resources.new_level_request = false;
+    resources.spatial_index = SpatialIndex::new(data.map_size.0,data.map_size.1);
+    resources.spatial_index.rebuild(world);
+    resources.replay = data.replay;
+    Ok(())
+}
@@ -1,82 +1,115 @@
-
-use hecs::*;
 use macroquad::prelude::*;
+use hecs::*;
 use crate::prelude::*;
 
+//messages and animations are positioned in tiles so the simulation never
+//needs to know how big the screen is
 pub struct ScreenMessage {
-    pub pos: (f32,f32),
+    pub tile: (i32,i32),
+    pub rise: f32,
     pub msg: String,
     pub color: Color,
 }
 
-pub fn draw_animations(world: &World) {
-    for (_,message) in world.query::<&ScreenMessage>().iter() {
+pub fn draw_animations(world: &World, resources: &Resources) {
+    let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
+    let camera = camera(world,resources);
+    for blast in resources.blasts.iter() {
+        let center = tile_center(camera,blast.center,tile_size);
+        let mut color = blast.color;
+        color.a *= 0.5;
+        draw_circle(center.0,center.1,(blast.radius as f32+0.5)*tile_size*blast.size,color);
+    }
+
+    for message in resources.screen_messages.iter() {
+        let (x,y) = map_to_screen(camera,message.tile,tile_size);
         draw_text(
             message.msg.as_str(),
-            message.pos.0,message.pos.1,
+            x,
+            y+(1.-message.rise)*tile_size,
             30.,
             message.color
         );
     }
 
-    for (_,animation) in world.query::<&RangedAttackAnimation>().iter() {
+    for animation in resources.animations.iter() {
+        let start = tile_center(camera,animation.start,tile_size);
+        let finish = tile_center(camera,animation.finish,tile_size);
         draw_line(
-            animation.start.0,
-            animation.start.1,
-            animation.finish.0,
-            animation.finish.1,
+            start.0,
+            start.1,
+            finish.0,
+            finish.1,
             3.,animation.color
         );
     }
 }
 
-pub fn animation_system(world: &mut World){
-    let mut messages_to_despawn = Vec::new();
-    for (ent,message) in world.query::<&mut ScreenMessage>().iter() {
-        message.pos.1 -= 0.3;
+fn tile_center(camera: (i32,i32), tile: (i32,i32), tile_size: f32) -> (f32,f32) {
+    let (x,y) = map_to_screen(camera,tile,tile_size);
+    (x+tile_size/2.,y+tile_size/2.)
+}
+
+pub fn animation_system(resources: &mut Resources){
+    for message in resources.screen_messages.iter_mut() {
+        message.rise += 0.01;
         message.color.a -= 0.01;
-        if message.color.a <= 0. {
-            messages_to_despawn.push(ent);
-        }
     }
+    resources.screen_messages.retain(|message|message.color.a > 0.);
 
-    for (ent,ranged_animation) in world.query::<&mut RangedAttackAnimation>().iter() {
+    for ranged_animation in resources.animations.iter_mut() {
         ranged_animation.color.a -= 0.03;
-        if ranged_animation.color.a <= 0. {
-            messages_to_despawn.push(ent);
-        }
     }
+    resources.animations.retain(|animation|animation.color.a > 0.);
 
-    messages_to_despawn.iter().for_each(|&e|{world.despawn(e).ok();});
-
+    for blast in resources.blasts.iter_mut() {
+        blast.size = (blast.size+0.08).min(1.);
+        blast.color.a -= 0.02;
+    }
+    resources.blasts.retain(|blast|blast.color.a > 0.);
 }
 
-pub fn emit_message(world: &mut World, tile: (i32,i32), msg: String,color: Color) {
-    let tile_size = screen_height()/(ARENA_HEIGHT as f32+2.);
-    world.spawn((
-        ScreenMessage {
-            pos: ((tile.0+1) as f32*tile_size,(tile.1+2) as f32*tile_size),
-            msg: msg.clone(),
-            color,
-        },
-    ));
-
+pub fn emit_message(resources: &mut Resources, tile: (i32,i32), msg: String,color: Color) {
+    resources.screen_messages.push(ScreenMessage {
+        tile,
+        rise: 0.,
+        msg,
+        color,
+    });
 }
 
 pub struct RangedAttackAnimation {
-    pub start: (f32,f32),
-    pub finish: (f32,f32),
+    pub start: (i32,i32),
+    pub finish: (i32,i32),
     pub color: Color,
 }
 
 pub fn emit_ranged_attack_animation(
-    world: &mut World, 
+    resources: &mut Resources,
     start_tile:(i32,i32),
     finish_tile:(i32,i32),
     color: Color
 ) {
-    let tile_size = screen_height()/(ARENA_HEIGHT as f32+2.);
-    let start = ((start_tile.0 as f32+1.5) *tile_size,(start_tile.1 as f32+1.5) *tile_size);
-    let finish = ((finish_tile.0 as f32+1.5) *tile_size,(finish_tile.1 as f32+1.5) *tile_size);
-    world.spawn((RangedAttackAnimation{start,finish,color},));
+    resources.animations.push(RangedAttackAnimation{
+        start: start_tile,
+        finish: finish_tile,
+        color
+    });
+}
+
+//a circle that grows out from the center tile and fades
+pub struct BlastAnimation {
+    pub center: (i32,i32),
+    pub radius: i32,
+    pub size: f32,
+    pub color: Color,
+}
+
+pub fn emit_blast_animation(resources: &mut Resources, center: (i32,i32), radius: i32, color: Color) {
+    resources.blasts.push(BlastAnimation{
+        center,
+        radius,
+        size: 0.2,
+        color,
+    });
 }
@@ -0,0 +1,33 @@
+use serde::{Serialize,Deserialize};
+
+const SETTINGS_PATH: &str = "settings.ron";
+
+//options picked from the main menu, kept between runs
+#[derive(Clone,Serialize,Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    //lets the player and monsters step diagonally
+    pub diagonals: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            diagonals: true,
+        }
+    }
+}
+
+//a missing or broken settings file just means the defaults
+pub fn load_settings() -> Settings {
+    std::fs::read_to_string(SETTINGS_PATH).ok()
+        .and_then(|text|ron::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &Settings) -> Result<(),String> {
+    let text = ron::ser::to_string_pretty(settings,ron::ser::PrettyConfig::default())
+        .map_err(|e|format!("could not serialize settings: {}",e))?;
+    std::fs::write(SETTINGS_PATH,text)
+        .map_err(|e|format!("could not write {}: {}",SETTINGS_PATH,e))
+}
@@ -0,0 +1,643 @@
+use hecs::*;
+use serde::{Serialize,Deserialize};
+use crate::prelude::*;
+use crate::combat::*;
+use crate::monster_memory::memory_system;
+use crate::dungeon_gen::*;
+use crate::replay::Replay;
+use macroquad::color::*;
+use macroquad::rand::*;
+
+//the turn logic of the game, free of any input handling or drawing so a
+//whole run can be played without a window
+
+pub enum RunStatus {
+    Playing,
+    Died,
+    Escaped,
+}
+
+pub fn new_game(world: &mut World, resources: &mut Resources, seed: u64) {
+    //a fresh world rather than a cleared one, so entity ids and archetype
+    //order are the same every time a seed is played
+    *world = World::new();
+    resources.level = 0;
+    resources.seed = seed;
+    resources.time = 0;
+    resources.player = crate::resources::PlayerData::new();
+    resources.message_log = MessageLog::default();
+    resources.replay = Some(Replay::new(seed,resources.settings.diagonals));
+    macroquad::rand::srand(seed);
+    start_with_health_pot(world,resources);
+    new_level(world,resources,seed);
+    log(resources,"You enter the tower".to_owned(),GOLD);
+}
+
+fn start_with_health_pot(world:&mut World,resources:&mut Resources) {
+    let ent = spawn(world,EntityKind::HealthPotion);
+    world.remove_one::<OnLevel>(ent).ok();
+    resources.player.inventory.push(ent);
+}
+
+//how long the player's actions take, in energy
+const PICK_UP_COST: i32 = ACTION_COST/2;
+const DROP_COST: i32 = ACTION_COST/2;
+const EQUIP_COST: i32 = ACTION_COST*3/2;
+
+//the player is slowed down past CARRY_CAPACITY and can't pick anything
+//up that would take them past MAX_LOAD
+pub const CARRY_CAPACITY: u32 = 100;
+pub const MAX_LOAD: u32 = CARRY_CAPACITY*3/2;
+
+//percent chance a fired arrow can be picked back up
+const ARROW_RECOVERY_CHANCE: i32 = 50;
+
+//plays out the player's actions and, if they took any time, the monsters' response
+pub fn take_turn(world: &mut World, resources: &mut Resources, actions: Vec<PlayerAction>) -> bool {
+    if let Some(replay) = &mut resources.replay {
+        if !actions.is_empty() {
+            replay.turns.push(actions.clone());
+        }
+    }
+    update_fov(world,resources,false);
+    let was_burdened = is_burdened(world,resources);
+    let time_taken = player_actions(world,resources,actions);
+    let action_taken = time_taken > 0;
+    match (was_burdened,is_burdened(world,resources)) {
+        (false,true) => log(resources,"You are burdened by everything you carry".to_owned(),YELLOW),
+        (true,false) => log(resources,"You are no longer burdened".to_owned(),LIGHTGRAY),
+        _ => {}
+    }
+    if action_taken {
+        let player = player(world).unwrap();
+        if let Ok(mut energy) = world.get_mut::<Energy>(player) {
+            energy.current -= time_taken;
+        }
+        run_until_player_ready(world,resources);
+    }
+
+    if resources.new_level_request {
+        let seed = resources.seed;
+        new_level(world,resources,seed);
+        resources.new_level_request = false;
+        let line = format!("You descend to floor {}",resources.level);
+        log(resources,line,GOLD);
+    }
+    update_fov(world,resources,false);
+    action_taken
+}
+
+//gives every actor energy one tick at a time, monsters act whenever they
+//have enough, until the player has enough to act again
+fn run_until_player_ready(world: &mut World, resources: &mut Resources) {
+    let player = player(world).unwrap();
+    let burdened = is_burdened(world,resources);
+    loop {
+        let ready: Vec<(Entity,Behavior)> = world.query::<(&Behavior,&Energy)>().iter()
+            .filter(|(_,(_,energy))|energy.current >= 0)
+            .map(|(ent,(behavior,_))|(ent,*behavior))
+            .collect();
+        for (actor,behavior) in ready {
+            //killed earlier in the same tick
+            if !world.contains(actor) {
+                continue;
+            }
+            update_fov(world,resources,false);
+            let cost = act(world,resources,actor,behavior);
+            if let Ok(mut energy) = world.get_mut::<Energy>(actor) {
+                energy.current -= cost;
+            }
+        }
+
+        if let Ok(health) = world.get::<Health>(player) {
+            if health.current <= 0 {
+                return;
+            }
+        }
+
+        for (ent,(energy,effects)) in world.query::<(&mut Energy,Option<&StatusEffects>)>().iter() {
+            energy.current += effective_speed(energy,effects,burdened && ent == player);
+        }
+        resources.time += 1;
+        if resources.time.is_multiple_of(TICKS_PER_TURN) {
+            memory_system(world,resources);
+            status_system(world,resources);
+        }
+
+        let player_ready = world.get::<Energy>(player)
+            .map(|energy|energy.current >= 0)
+            .unwrap_or(true);
+        if player_ready {
+            return;
+        }
+    }
+}
+
+pub fn run_status(world: &World, resources: &Resources) -> RunStatus {
+    if let Ok(player) = player(world) {
+        if let Ok(health) = world.get::<Health>(player) {
+            if health.current <= 0 {
+                return RunStatus::Died;
+            }
+        }
+    }
+
+    if resources.level > 10 {
+        return RunStatus::Escaped;
+    }
+    RunStatus::Playing
+}
+
+pub fn new_level(world:&mut World,resources: &mut Resources,seed: u64) {
+    resources.level += 1;
+    use macroquad::rand::*;
+    let seed = level_seed(seed,resources.level);
+    srand(seed);
+    
+    //remove old level
+    let entities_to_despawn: Vec<Entity> = world.query::<&OnLevel>().iter()
+        .map(|(e,_)|e.clone()).collect();
+
+    entities_to_despawn.iter().for_each(|&e|{world.despawn(e).ok();});
+
+    //create new level
+    let map = build_floor(resources.level,seed);
+    resources.spatial_index = SpatialIndex::new(map.width,map.height);
+
+    //load new level to world
+    let pos = map.load_to_world(world).unwrap_or((10,10));
+
+    if let Ok(player_id) = player(world) {
+        if let Ok(mut player_pos) = world.get_mut::<Pos>(player_id) {
+            player_pos.x = pos.0 as i32;
+            player_pos.y = pos.1 as i32;
+        } 
+    } else {
+        spawn_at(world,EntityKind::Player,Pos::new(pos.0 as i32,pos.1 as i32));
+    }
+    resources.spatial_index.rebuild(world);
+}
+
+#[derive(Copy,Clone,Debug,Serialize,Deserialize)]
+pub enum PlayerAction {
+    TryWalk(Dir),
+    PickUpItem(Entity),
+    DropItem(Entity),
+    //drops a single item off a stack and keeps the rest
+    DropOne(Entity),
+    EquipItem(Entity),
+    //takes off whatever is in the slot and puts it back in the inventory
+    Unequip(Equipable),
+    //the target tile is filled in when the item has to be aimed
+    UseItem(Entity,Option<(i32,i32)>),
+    //shoots the equipped ranged weapon at a tile
+    Fire((i32,i32)),
+    Wait,
+}
+
+//the first ammo in the inventory, which is what the bow fires
+fn carried_ammo(world: &World, resources: &Resources) -> Option<Entity> {
+    resources.player.inventory.iter()
+        .find(|ent|world.get::<Ammo>(**ent).is_ok())
+        .copied()
+}
+
+//how much an item weighs, all of it for a stack
+fn item_weight(world: &World, ent: Entity) -> u32 {
+    let weight = get_cloned::<Weight>(world,ent).map(|weight|weight.weight).unwrap_or(0);
+    let count = get_cloned::<Stack>(world,ent).map(|stack|stack.count).unwrap_or(1);
+    weight*count
+}
+
+//everything in the inventory plus whatever the player has equipped
+pub fn carried_weight(world: &World, resources: &Resources) -> u32 {
+    let equipped = player(world).ok()
+        .and_then(|player|get_cloned::<Equipment>(world,player))
+        .map(|equipment|vec![equipment.weapon,equipment.armor])
+        .unwrap_or_default();
+    resources.player.inventory.iter().copied()
+        .chain(equipped.into_iter().flatten())
+        .map(|ent|item_weight(world,ent))
+        .sum()
+}
+
+pub fn is_burdened(world: &World, resources: &Resources) -> bool {
+    carried_weight(world,resources) > CARRY_CAPACITY
+}
+
+//shoots an arrow from the equipped weapon, returns the time it took
+fn fire(world: &mut World, resources: &mut Resources, player: Entity, tile: (i32,i32)) -> i32 {
+    let attack_data = match get_ranged_weapon(world,player) {
+        Some(attack_data) => attack_data,
+        None => {
+            log(resources,"You have nothing to fire".to_owned(),GRAY);
+            return 0;
+        }
+    };
+    let arrows = match carried_ammo(world,resources) {
+        Some(arrows) => arrows,
+        None => {
+            log(resources,"You are out of arrows".to_owned(),GRAY);
+            return 0;
+        }
+    };
+    let origin: (i32,i32) = get_cloned::<Pos>(world,player).unwrap().into();
+    let in_range = match (attack_data.range.clone(),unblocked_attack_line(world,origin,tile)) {
+        (Range::Ranged(range),Some((_,distance))) => distance as i32 <= range,
+        _ => false,
+    };
+    if !in_range || tile == origin {
+        log(resources,"You can't shoot there".to_owned(),GRAY);
+        return 0;
+    }
+
+    let arrow = take_one(world,resources,arrows);
+
+    let target = get_entities_at(resources,tile).into_iter()
+        .find(|ent|world.get::<Health>(*ent).is_ok());
+    if let Some(target) = target {
+        attack(world,resources,player,target,attack_data.clone());
+    } else {
+        log(resources,"Your arrow hits nothing".to_owned(),GRAY);
+        emit_ranged_attack_animation(resources,origin,tile,LIGHTGRAY);
+    }
+
+    if gen_range(0,100) < ARROW_RECOVERY_CHANCE {
+        place_item(world,resources,arrow,tile);
+    } else {
+        world.despawn(arrow).ok();
+    }
+    attack_data.cost
+}
+
+//the stack out of candidates that holds the same items as ent
+fn matching_stack(world: &World, candidates: impl Iterator<Item=Entity>, ent: Entity) -> Option<Entity> {
+    let stack = get_cloned::<Stack>(world,ent)?;
+    candidates
+        .filter(|other|*other != ent)
+        .find(|other|{
+            world.get::<Stack>(*other)
+                .map(|other|other.template == stack.template)
+                .unwrap_or(false)
+        })
+}
+
+//adds ent's count onto the stack and despawns it
+fn merge_into(world: &mut World, ent: Entity, stack: Entity) {
+    let count = get_cloned::<Stack>(world,ent).map(|stack|stack.count).unwrap_or(1);
+    if let Ok(mut stack) = world.get_mut::<Stack>(stack) {
+        stack.count += count;
+    }
+    world.despawn(ent).ok();
+}
+
+//picked up items go onto a carried stack of the same items if there is one
+fn add_to_inventory(world: &mut World, resources: &mut Resources, ent: Entity) {
+    remove_pos(world,&mut resources.spatial_index,ent);
+    world.remove_one::<OnLevel>(ent).ok();
+    match matching_stack(world,resources.player.inventory.iter().copied(),ent) {
+        Some(carried) => merge_into(world,ent,carried),
+        None => resources.player.inventory.push(ent),
+    }
+}
+
+//dropped and thrown items pile up with the same items on the tile
+fn place_item(world: &mut World, resources: &mut Resources, ent: Entity, tile: (i32,i32)) {
+    let on_tile = get_entities_at(resources,tile);
+    match matching_stack(world,on_tile.into_iter(),ent) {
+        Some(pile) => merge_into(world,ent,pile),
+        None => {
+            set_pos(world,&mut resources.spatial_index,ent,tile);
+            world.insert_one(ent,OnLevel).ok();
+        }
+    }
+}
+
+//splits one item off a carried stack, or takes the item out of the
+//inventory if it was the last one
+fn take_one(world: &mut World, resources: &mut Resources, ent: Entity) -> Entity {
+    let split = match world.get_mut::<Stack>(ent) {
+        Ok(mut stack) if stack.count > 1 => {
+            stack.count -= 1;
+            Some(stack.template.clone())
+        }
+        _ => None,
+    };
+    match split {
+        Some(template) => {
+            let one = spawn_template(world,&template);
+            world.remove_one::<OnLevel>(one).ok();
+            if let Ok(mut stack) = world.get_mut::<Stack>(one) {
+                stack.count = 1;
+            }
+            one
+        }
+        None => {
+            resources.player.inventory.retain(|item|*item != ent);
+            ent
+        }
+    }
+}
+
+fn move_player(world: &mut World, resources: &mut Resources, player: Entity, tile: (i32,i32)) {
+    if let Some(pos) = get_cloned::<Pos>(world,player) {
+        emit_blast_animation(resources,(pos.x,pos.y),0,SKYBLUE);
+    }
+    set_pos(world,&mut resources.spatial_index,player,tile);
+    emit_blast_animation(resources,tile,0,SKYBLUE);
+    update_fov(world,resources,false);
+}
+
+//the tile a blink toward target ends on, if the target is taken by a
+//monster the blink stops on the last free tile before it
+fn blink_destination(
+    world: &World,
+    resources: &Resources,
+    origin: (i32,i32),
+    target: (i32,i32),
+    range: i32,
+) -> Option<(i32,i32)> {
+    if !resources.fov_set.contains(&target) {
+        return None;
+    }
+    let (path,distance) = unblocked_attack_line(world,origin,target)?;
+    if distance as i32 > range {
+        return None;
+    }
+    let walkable = walkable_tiles(world,resources);
+    path.into_iter().rev()
+        .find(|tile|walkable.binary_search(tile).is_ok())
+}
+
+//returns how much energy the actions took, nothing happened if it is 0
+pub fn player_actions(world: &mut World, resources: &mut Resources, actions: Vec<PlayerAction>) -> i32 {
+    let mut time_taken = 0;
+    let player = player(world).unwrap();
+    let player_position = {
+        let player_position = world.get::<Pos>(player).unwrap();
+        Pos::new(player_position.x,player_position.y)
+    };
+    for action in actions {
+        match action {
+            PlayerAction::Wait => {
+                time_taken += ACTION_COST;
+            }
+            PlayerAction::TryWalk(dir) => {
+                let diagonals = resources.settings.diagonals;
+                if dir.is_diagonal() && !diagonals {
+                    continue;
+                }
+                //confusion sends half of the player's steps somewhere random
+                let dir = if has_status(world,player,StatusKind::Confusion) && gen_range(0,2) == 0 {
+                    let dirs = Dir::directions(diagonals);
+                    dirs.get(gen_range(0,dirs.len())).copied().unwrap_or(dir)
+                } else {
+                    dir
+                };
+                let pos = get_cloned::<Pos>(world,player).unwrap();
+                let (dx,dy) = dir.components();
+                let destination = (pos.x + dx,pos.y + dy);
+                let bumped = bump_at(world,resources,destination);
+                if bumped.is_none() {
+                    set_pos(world,&mut resources.spatial_index,player,destination);
+                    time_taken += ACTION_COST;
+                }
+
+                if let Some((ent,bump)) = bumped {
+                    match bump {
+                        Bump::OpenDoor => {
+                            world.remove_one::<Bump>(ent).ok();
+                            world.remove_one::<BlocksSight>(ent).ok();
+                            if let Ok(mut appearance) = world.get_mut::<Appearance>(ent) {
+                                appearance.sprite = 7;
+                            }
+                            time_taken += ACTION_COST;
+                        }
+                        Bump::Attack => {
+                            let mut attacks_list: Vec<(Entity,AttackData)> = Vec::new();
+                            if let Some(attack_data) = get_melee_attack(world,player) {
+                                if attack_data.axe {
+                                    let adjacent_tiles: Vec<(i32,i32)> = 
+                                    [(-1,-1),(0,-1,),(1,-1),
+                                     (-1,0),         (1,0),
+                                     (-1,1), (0,1),  (1,1)].iter().map(|diff|{
+                                         (player_position.x+diff.0,
+                                         player_position.y+diff.1)
+                                    }).collect();
+
+                                    for tile in adjacent_tiles {
+                                        if let Some((ent,bump)) = bump_at(world,resources,tile) {
+                                            match bump {
+                                                Bump::Attack => {
+                                                    attacks_list.push((ent,attack_data.clone()));
+                                                },
+                                                _ => {}
+                                            }
+                                        }
+                                    }
+
+                                } else {
+                                    attacks_list.push((ent,attack_data.clone()));
+                                }
+                                //one swing, however many it hits
+                                time_taken += attack_data.cost;
+                            }
+                            for (ent,attack_data) in attacks_list {
+                                attack(world,resources,player,ent,attack_data);
+                            }
+                        }
+                        Bump::NextLevel => {
+                            resources.new_level_request = true;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            PlayerAction::PickUpItem(ent) => {
+                if carried_weight(world,resources)+item_weight(world,ent) > MAX_LOAD {
+                    log(resources,format!("{} is too heavy to carry with everything else",capitalize(&log_name(world,ent))),GRAY);
+                    continue;
+                }
+                log(resources,format!("You pick up {}",log_name(world,ent)),LIGHTGRAY);
+                add_to_inventory(world,resources,ent);
+                time_taken += PICK_UP_COST;
+            }
+            PlayerAction::Fire(tile) => {
+                time_taken += fire(world,resources,player,tile);
+            }
+            PlayerAction::DropItem(ent) => {
+                log(resources,format!("You drop {}",log_name(world,ent)),LIGHTGRAY);
+                resources.player.inventory.retain(|item|*item != ent);
+                place_item(world,resources,ent,player_position.into());
+                time_taken += DROP_COST;
+            }
+            PlayerAction::DropOne(ent) => {
+                let one = take_one(world,resources,ent);
+                log(resources,format!("You drop {}",log_name(world,one)),LIGHTGRAY);
+                place_item(world,resources,one,player_position.into());
+                time_taken += DROP_COST;
+            }
+            PlayerAction::EquipItem(ent) => {
+                let mut equipable = None;
+                if let Ok(item) = world.get::<Equipable>(ent) {
+                    equipable.replace((*item).clone());
+                }
+                if let Some(equipable) = equipable {
+                    log(resources,format!("You equip {}",log_name(world,ent)),LIGHTGRAY);
+                    if let Ok(mut player_equipment) = world.get_mut::<Equipment>(player) {
+                        match equipable {
+                            Equipable::Armor => {
+                                if let Some(currently_equiped) = player_equipment.armor {
+                                    resources.player.inventory.push(currently_equiped);
+                                }
+                                player_equipment.armor.replace(ent);
+                            },
+                            Equipable::Weapon => {
+                                if let Some(currently_equiped) = player_equipment.weapon {
+                                    resources.player.inventory.push(currently_equiped);
+                                }
+                                player_equipment.weapon.replace(ent);
+
+                            }
+                        }
+                        let mut index_to_remove = Vec::new();
+                        for (i,e) in resources.player.inventory.iter().enumerate() {
+                            if *e == ent {
+                                index_to_remove.push(i);
+                            }
+                        }
+                        for i in index_to_remove {
+                            resources.player.inventory.remove(i);
+                        }
+                    }
+                }
+                time_taken += EQUIP_COST;
+            }
+            PlayerAction::Unequip(slot) => {
+                let removed = world.get_mut::<Equipment>(player).ok()
+                    .and_then(|mut equipment|match slot {
+                        Equipable::Weapon => equipment.weapon.take(),
+                        Equipable::Armor => equipment.armor.take(),
+                    });
+                if let Some(item) = removed {
+                    log(resources,format!("You take off {}",log_name(world,item)),LIGHTGRAY);
+                    resources.player.inventory.push(item);
+                    time_taken += EQUIP_COST;
+                }
+            }
+            PlayerAction::UseItem(ent,target) => {
+                let useable: Option<Useable> = get_cloned(world,ent);
+                if let Some(useable) = useable {
+                    let mut item_used = false;
+                    let mut landed = None;
+                    match useable {
+                        Useable::Heal => {
+                            log(resources,format!("You drink the {} and feel better",log_name(world,ent)),LIGHTGRAY);
+                            if let Ok(mut health) = world.get_mut::<Health>(player) {
+                                health.current = health.max;
+                            }
+                            item_used = true;
+                            time_taken += ACTION_COST;
+                        }
+                        Useable::Speed => {
+                            apply_status(world,resources,player,StatusEffect{
+                                kind: StatusKind::Haste,
+                                turns: POTION_TURNS,
+                            });
+                            item_used = true;
+                            time_taken += ACTION_COST;
+                        }
+                        Useable::Strength => {
+                            apply_status(world,resources,player,StatusEffect{
+                                kind: StatusKind::Strength,
+                                turns: POTION_TURNS,
+                            });
+                            item_used = true;
+                            time_taken += ACTION_COST;
+                        }
+                        Useable::Regeneration => {
+                            apply_status(world,resources,player,StatusEffect{
+                                kind: StatusKind::Regeneration,
+                                turns: POTION_TURNS,
+                            });
+                            item_used = true;
+                            time_taken += ACTION_COST;
+                        }
+                        Useable::MagicMapping => {
+                            log(resources,format!("You read the {}, the floor's layout is revealed",log_name(world,ent)),LIGHTGRAY);
+                            update_fov(world,resources,true);
+                            update_fov(world,resources,false);
+                            item_used = true;
+                            time_taken += ACTION_COST;
+                        }
+                        Useable::Fireball(attack_data) => {
+                            if let Some(tile) = target {
+                                item_used = true;
+                                time_taken += attack_data.cost;
+                                log(resources,format!("You read the {}, fire bursts out",log_name(world,ent)),ORANGE);
+                                fireball(world,resources,player,tile,attack_data);
+                            }
+                        }
+                        Useable::Teleport => {
+                            let tiles: Vec<(i32,i32)> = walkable_tiles(world,resources).into_iter()
+                                .filter(|tile|*tile != player_position.into())
+                                .collect();
+                            if let Some(tile) = tiles.get(gen_range(0,tiles.len())) {
+                                log(resources,format!("You read the {} and vanish",log_name(world,ent)),SKYBLUE);
+                                move_player(world,resources,player,*tile);
+                            } else {
+                                log(resources,"The scroll fizzles, there is nowhere to go".to_owned(),GRAY);
+                            }
+                            item_used = true;
+                            time_taken += ACTION_COST;
+                        }
+                        Useable::Blink(range) => {
+                            if let Some(tile) = target {
+                                if let Some(tile) = blink_destination(world,resources,player_position.into(),tile,range) {
+                                    log(resources,format!("You read the {} and blink away",log_name(world,ent)),SKYBLUE);
+                                    emit_ranged_attack_animation(resources,player_position.into(),tile,SKYBLUE);
+                                    move_player(world,resources,player,tile);
+                                    item_used = true;
+                                    time_taken += ACTION_COST;
+                                } else {
+                                    log(resources,"You can't blink there".to_owned(),GRAY);
+                                }
+                            }
+                        }
+                        Useable::Throw(attack_data,mulch) => {
+                            let mut targets = Vec::new();
+                            if let Some(tile) = target {
+                                item_used = true;
+                                time_taken += attack_data.cost;
+                                log(resources,format!("You throw the {}",log_name(world,ent)),LIGHTGRAY);
+                                for target_ent in get_entities_at(resources,tile) {
+                                    if let Ok(_) = world.get::<Health>(target_ent) {
+                                        targets.push(target_ent);
+                                    }
+                                }
+                                if !mulch {
+                                    landed.replace(tile);
+                                }
+                            }
+
+                            for target in targets {
+                                attack(world,resources,player,target,attack_data.clone());
+                            }
+
+                            
+                        }
+                    }
+                    if item_used {
+                        let used = take_one(world,resources,ent);
+                        match landed {
+                            Some(tile) => place_item(world,resources,used,tile),
+                            None => {world.despawn(used).ok();}
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    time_taken
+}
@@ -0,0 +1,94 @@
+use hecs::*;
+use crate::components::Pos;
+
+//the entities standing on each tile of the level, so tile lookups don't
+//have to scan the whole world. Every change to an entity's Pos goes through
+//set_pos, remove_pos or despawn to keep the two in sync
+pub struct SpatialIndex {
+    width: i32,
+    height: i32,
+    cells: Vec<Vec<Entity>>,
+}
+
+impl SpatialIndex {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width: width as i32,
+            height: height as i32,
+            cells: vec![Vec::new();width*height],
+        }
+    }
+
+    pub fn size(&self) -> (usize,usize) {
+        (self.width as usize,self.height as usize)
+    }
+
+    fn cell(&self, tile: (i32,i32)) -> Option<usize> {
+        if tile.0 < 0 || tile.1 < 0 || tile.0 >= self.width || tile.1 >= self.height {
+            return None;
+        }
+        Some((tile.0+tile.1*self.width) as usize)
+    }
+
+    pub fn at(&self, tile: (i32,i32)) -> &[Entity] {
+        match self.cell(tile) {
+            Some(cell) => &self.cells[cell],
+            None => &[],
+        }
+    }
+
+    pub fn insert(&mut self, ent: Entity, tile: (i32,i32)) {
+        if let Some(cell) = self.cell(tile) {
+            self.cells[cell].push(ent);
+        }
+    }
+
+    pub fn remove(&mut self, ent: Entity, tile: (i32,i32)) {
+        if let Some(cell) = self.cell(tile) {
+            self.cells[cell].retain(|other|*other != ent);
+        }
+    }
+
+    //every tile on the grid, column by column so they come out sorted
+    pub fn tiles(&self) -> impl Iterator<Item=(i32,i32)> {
+        let (width,height) = (self.width,self.height);
+        (0..width).flat_map(move |x|(0..height).map(move |y|(x,y)))
+    }
+
+    //for after the world has been filled in bulk, like a new level or a
+    //loaded save
+    pub fn rebuild(&mut self, world: &World) {
+        self.cells.iter_mut().for_each(|cell|cell.clear());
+        for (ent,pos) in world.query::<&Pos>().iter() {
+            self.insert(ent,(pos.x,pos.y));
+        }
+    }
+}
+
+//moves an entity to a tile, giving it a Pos if it didn't have one
+pub fn set_pos(world: &mut World, index: &mut SpatialIndex, ent: Entity, tile: (i32,i32)) {
+    let moved = if let Ok(mut pos) = world.get_mut::<Pos>(ent) {
+        index.remove(ent,(pos.x,pos.y));
+        pos.x = tile.0;
+        pos.y = tile.1;
+        true
+    } else {
+        false
+    };
+    if !moved && world.insert_one(ent,Pos::new(tile.0,tile.1)).is_err() {
+        return;
+    }
+    index.insert(ent,tile);
+}
+
+//takes an entity off the map, like an item being picked up
+pub fn remove_pos(world: &mut World, index: &mut SpatialIndex, ent: Entity) {
+    if let Ok(pos) = world.remove_one::<Pos>(ent) {
+        index.remove(ent,(pos.x,pos.y));
+    }
+}
+
+pub fn despawn(world: &mut World, index: &mut SpatialIndex, ent: Entity) {
+    remove_pos(world,index,ent);
+    world.despawn(ent).ok();
+}
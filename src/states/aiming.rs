@@ -5,9 +5,9 @@ use crate::combat::*;
 
 
 pub async fn aiming_state(
-    world: &mut World,
-    resources: &mut Resources,
-    _attack_data: &AttackData,
+    world: &World,
+    resources: &Resources,
+    attack_data: &AttackData,
 ) -> Option<(i32,i32)> {
     next_frame().await;
     let player = player(world).unwrap();
@@ -25,15 +25,32 @@ pub async fn aiming_state(
         }
         draw(world,resources);
 
-        if let Some(mouse_tile) = mouse_to_map() {
+        if let Some(mouse_tile) = mouse_to_map(world,resources) {
             if let Some((path,distance)) = unblocked_attack_line(
                 world,player_position.into(),mouse_tile
             ) {
-                let tile_size = screen_height()/(ARENA_HEIGHT as f32+2.);
-                let start = ((player_position.x as f32+1.5) *tile_size,(player_position.y as f32+1.5) *tile_size);
-                let finish = ((mouse_tile.0 as f32+1.5) *tile_size,(mouse_tile.1 as f32+1.5) *tile_size);
-                draw_line(start.0,start.1,finish.0,finish.1,3.,WHITE);
-                if is_mouse_button_pressed(MouseButton::Left) {
+                let in_range = match attack_data.range {
+                    Range::Ranged(range) => distance as i32 <= range,
+                    Range::Meele => true,
+                };
+                let line_color = if in_range {WHITE} else {GRAY};
+                let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
+                let camera = camera(world,resources);
+                let start = map_to_screen(camera,player_position.into(),tile_size);
+                let finish = map_to_screen(camera,mouse_tile,tile_size);
+                let half = tile_size/2.;
+                draw_line(start.0+half,start.1+half,finish.0+half,finish.1+half,3.,line_color);
+                //show what the blast would catch
+                if attack_data.fireball && in_range {
+                    for tile in blast_tiles(world,mouse_tile,attack_data.radius) {
+                        let (x,y) = map_to_screen(camera,tile,tile_size);
+                        draw_rectangle(
+                            x,y,
+                            tile_size,tile_size,
+                            Color::new(1.,0.6,0.,0.35));
+                    }
+                }
+                if in_range && is_mouse_button_pressed(MouseButton::Left) {
                     next_frame().await;
                     return Some(mouse_tile);
                 }
@@ -41,7 +58,6 @@ pub async fn aiming_state(
         }
         next_frame().await
     }
-    dbg!();
     next_frame().await;
     None
 }
@@ -1,128 +1,163 @@
 use macroquad::prelude::*;
 use hecs::*;
 use crate::prelude::*;
-use crate::combat::*;
 use crate::states::inventory::inventory_state;
 use crate::states::aiming::aiming_state;
+use crate::states::look::look_state;
 use crate::states::game_controls::control_screen;
 use super::{GameState,StateChange};
-use crate::monster_memory::memory_system;
-use crate::dungeon_gen::*;
+use crate::simulation::*;
+use crate::save::*;
+use crate::replay::*;
+use crate::combat::{get_ranged_weapon,AttackData,Range};
+use std::collections::HashSet;
 
 pub async fn game(
     world: &mut World,
     resources: &mut Resources,
 ) -> StateChange {
+    let mut saved_level = 0;
+    let mut travel: Option<Travel> = None;
+    //monsters already in sight when a walk starts, only new ones stop it
+    let mut seen_monsters: HashSet<Entity> = HashSet::new();
     loop {
-        if resources.level == 0 ||
-            resources.new_level_request
-            //is_key_pressed(KeyCode::P) 
-        {
-            new_level(world,resources);
-            resources.new_level_request = false;
+        //autosave whenever a new floor is reached
+        if resources.level != saved_level {
+            if let Err(e) = save_game(world,resources) {
+                log(resources,e,RED);
+            }
+            saved_level = resources.level;
         }
         if is_key_pressed(KeyCode::C) {
             control_screen().await;
         }
-        /*
-        if is_key_pressed(KeyCode::M) {
-            emit_ranged_attack_animation(world,(0,0),(5,0),WHITE);
-            dbg!();
+        if is_key_pressed(KeyCode::Escape) {
+            if let Err(e) = save_game(world,resources) {
+                log(resources,e,RED);
+            }
+            record_replay(resources);
+            return StateChange::Replace(GameState::MainMenu);
         }
-        */
+        scroll_message_log(resources);
         update_fov(world,resources,false);
-        let actions = player_input(world,resources).await;
-        if player_actions(world,resources,actions).await {
-            memory_system(world,resources);
-            //moster actions
-            let mut behaviors = Vec::new();
-            for (ent,behavior) in world.query::<&Behavior>().iter() {
-                behaviors.push((ent,behavior.clone()));
-            }
-            for (actor,behavior) in behaviors.iter() {
-                update_fov(world,resources,false);
-                act(world,resources,*actor,*behavior);
+        let starting = travel.is_none();
+        let actions = match travel {
+            //any key or click cancels a walk in progress
+            Some(_) if get_last_key_pressed().is_some() ||
+                is_mouse_button_pressed(MouseButton::Left) ||
+                is_mouse_button_pressed(MouseButton::Right) => {
+                    travel = None;
+                    Vec::new()
+                }
+            Some(destination) => {
+                let step = travel_step(world,resources,destination);
+                if step.is_none() {
+                    travel = None;
+                }
+                step.into_iter().collect()
             }
-
+            None => player_input(world,resources,&mut travel).await,
+        };
+        if starting && travel.is_some() {
+            seen_monsters = monsters_in_view(world);
+        }
+        let health = get_cloned::<Health>(world,player(world).unwrap()).map(|health|health.current);
+        let level = resources.level;
+        let action_taken = take_turn(world,resources,actions);
+        //auto-explore starts without a step and takes its first one next
+        //frame, and only an item it walks onto stops it, not the one it left
+        if travel.is_some() && (
+            (!action_taken && !starting) ||
+            level != resources.level ||
+            get_cloned::<Health>(world,player(world).unwrap()).map(|health|health.current) < health ||
+            !monsters_in_view(world).is_subset(&seen_monsters) ||
+            (action_taken && item_underfoot(world,resources))
+        ) {
+            travel = None;
         }
 
-        let player = player(world).unwrap();
-        if let Ok(health) = world.get::<Health>(player) {
-            if health.current <= 0 {
+        match run_status(world,resources) {
+            RunStatus::Died => {
+                delete_save();
+                record_replay(resources);
                 return StateChange::Replace(GameState::GameOver);
             }
+            RunStatus::Escaped => {
+                delete_save();
+                record_replay(resources);
+                return StateChange::Replace(GameState::Win);
+            }
+            RunStatus::Playing => {}
         }
 
-        if resources.level > 10 {
-            return StateChange::Replace(GameState::Win);
-        }
-
-
-
-        update_fov(world,resources,false);
         highlight_mouse_movement(world,resources);
         draw_map_and_hud(world,resources);
-        animation_system(world);
-        draw_animations(world);
+        animation_system(resources);
+        draw_animations(world,resources);
         clear_hightlights(resources);
         next_frame().await
     }
 }
 
-fn reset_game(world:&mut World,resources: &mut Resources) {
-    world.clear();
-    resources.level = 0;
-    use crate::resources::PlayerData;
-    resources.player = PlayerData::new();
-}
-
-fn new_level(world:&mut World,resources: &mut Resources) {
-    resources.level += 1;
-    use macroquad::rand::*;
-    
-    //remove old level
-    let entities_to_despawn: Vec<Entity> = world.query::<&OnLevel>().iter()
-        .map(|(e,_)|e.clone()).collect();
-
-    entities_to_despawn.iter().for_each(|&e|{world.despawn(e).ok();});
-
-    //create new level
-    let mut template = DUNGEON_TEMPLATES.choose().unwrap().clone();
-    if gen_range(0,2) as u32 == 0 {
-        template.transpose();
-    }
-    for _ in 0..gen_range(0,4) as u32 {
-        template.rotate();
+fn record_replay(resources: &mut Resources) {
+    if let Some(replay) = &resources.replay {
+        if let Err(e) = save_replay(replay,REPLAY_PATH) {
+            log(resources,e,RED);
+        }
     }
-    let map = QuadrantMap::new(resources.level,template).build();
+}
 
-    //load new level to world
-    let pos = map.load_to_world(world).unwrap_or((10,10));
+//where the player is walking to over several turns
+#[derive(Copy,Clone)]
+enum Travel {
+    To((i32,i32)),
+    Explore,
+}
 
-    if let Ok(player_id) = player(world) {
-        if let Ok(mut player_pos) = world.get_mut::<Pos>(player_id) {
-            player_pos.x = pos.0 as i32;
-            player_pos.y = pos.1 as i32;
-        } 
-    } else {
-        spawn_at(world,EntityKind::Player,Pos::new(pos.0 as i32,pos.1 as i32));
-    }
+//the next step toward the destination, none once there or stuck
+fn travel_step(world: &World, resources: &mut Resources, travel: Travel) -> Option<PlayerAction> {
+    use crate::movement::my_pathfinding::{path,unexplored_path};
+    let player = player(world).unwrap();
+    let start: (i32,i32) = get_cloned::<Pos>(world,player)?.into();
+    let diagonals = resources.settings.diagonals;
+    let steps = match travel {
+        Travel::To(destination) => path(world,resources,start,destination,diagonals)?.0,
+        Travel::Explore => {
+            let mut explored: HashSet<(i32,i32)> = world.query::<(&Pos,&PlayerMemory)>().iter()
+                .map(|(_,(pos,_))|(pos.x,pos.y))
+                .collect();
+            explored.extend(resources.fov_set.iter().copied());
+            match unexplored_path(world,resources,start,&explored,diagonals) {
+                Some(steps) => steps,
+                None => {
+                    log(resources,"There is nothing left to explore here".to_owned(),GRAY);
+                    return None;
+                }
+            }
+        }
+    };
+    let next = steps.get(1)?;
+    Dir::from_components((next.0-start.0,next.1-start.1)).map(PlayerAction::TryWalk)
 }
 
+fn monsters_in_view(world: &World) -> HashSet<Entity> {
+    world.query::<(&Appearance,&Behavior)>().iter()
+        .filter(|(_,(appearance,_))|appearance.in_fov)
+        .map(|(ent,_)|ent)
+        .collect()
+}
 
-#[derive(Copy,Clone)]
-pub enum PlayerAction {
-    TryWalk(Dir),
-    PickUpItem(Entity),
-    DropItem(Entity),
-    EquipItem(Entity),
-    UseItem(Entity),
-    Wait,
+fn item_underfoot(world: &World, resources: &Resources) -> bool {
+    let player = player(world).unwrap();
+    let tile: (i32,i32) = get_cloned::<Pos>(world,player).unwrap().into();
+    get_entities_at(resources,tile).into_iter()
+        .any(|ent|world.get::<Item>(ent).is_ok())
 }
 
 async fn player_input (
     world: &World,
     resources: &Resources,
+    travel: &mut Option<Travel>,
 ) -> Vec<PlayerAction> {
     let player = player(world).unwrap();
     let player_position = get_cloned::<Pos>(world,player).unwrap();
@@ -140,7 +175,22 @@ async fn player_input (
     if is_key_pressed(KeyCode::D) {
         actions.push(PlayerAction::TryWalk(Dir::E));
     }
-    if is_key_pressed(KeyCode::Period) || is_key_pressed(KeyCode::Space) {
+    //vi keys and the numpad for diagonals
+    for (keys,dir) in [
+        ([KeyCode::Y,KeyCode::Kp7],Dir::NW),
+        ([KeyCode::U,KeyCode::Kp9],Dir::NE),
+        ([KeyCode::B,KeyCode::Kp1],Dir::SW),
+        ([KeyCode::N,KeyCode::Kp3],Dir::SE),
+        ([KeyCode::Kp8,KeyCode::Up],Dir::N),
+        ([KeyCode::Kp4,KeyCode::Left],Dir::W),
+        ([KeyCode::Kp2,KeyCode::Down],Dir::S),
+        ([KeyCode::Kp6,KeyCode::Right],Dir::E),
+    ] {
+        if keys.iter().any(|key|is_key_pressed(*key)) {
+            actions.push(PlayerAction::TryWalk(dir));
+        }
+    }
+    if is_key_pressed(KeyCode::Period) || is_key_pressed(KeyCode::Space) || is_key_pressed(KeyCode::Kp5) {
         actions.push(PlayerAction::Wait);
     }
     if is_key_pressed(KeyCode::Comma) || is_key_pressed(KeyCode::G) || is_key_pressed(KeyCode::E) {
@@ -158,13 +208,57 @@ async fn player_input (
         }
     }
 
+    if is_key_pressed(KeyCode::F) {
+        if let Some(attack_data) = get_ranged_weapon(world,player) {
+            if let Some(tile) = aiming_state(world,resources,&attack_data).await {
+                actions.push(PlayerAction::Fire(tile));
+            }
+        }
+    }
+
+    if is_key_pressed(KeyCode::O) {
+        travel.replace(Travel::Explore);
+    }
+
+    if is_key_pressed(KeyCode::L) || is_key_pressed(KeyCode::X) {
+        look_state(world,resources,player_position.into()).await;
+    }
+
     if is_key_pressed(KeyCode::I) || is_key_pressed(KeyCode::Tab) {
-        let mut returned_actions = inventory_state(world,resources).await;
-        actions.append(&mut returned_actions);
+        let returned_actions = inventory_state(world,resources).await;
+        for action in returned_actions {
+            match action {
+                //thrown items need a target before the action can happen
+                PlayerAction::UseItem(ent,None) => {
+                    match get_cloned::<Useable>(world,ent) {
+                        Some(Useable::Throw(attack_data,_)) | Some(Useable::Fireball(attack_data)) => {
+                            if let Some(tile) = aiming_state(world,resources,&attack_data).await {
+                                actions.push(PlayerAction::UseItem(ent,Some(tile)));
+                            }
+                        }
+                        Some(Useable::Blink(range)) => {
+                            let reach = AttackData{
+                                range: Range::Ranged(range),
+                                ..Default::default()
+                            };
+                            if let Some(tile) = aiming_state(world,resources,&reach).await {
+                                actions.push(PlayerAction::UseItem(ent,Some(tile)));
+                            }
+                        }
+                        _ => {
+                            actions.push(action);
+                        }
+                    }
+                }
+                _ => {
+                    actions.push(action);
+                }
+            }
+        }
     }
 
     if is_mouse_button_pressed(MouseButton::Left) {
-        if let Some(mouse_pos) = mouse_to_map() {
+        if let Some(mouse_pos) = mouse_to_map(world,resources) {
             if resources.fov_set.contains(&mouse_pos) {
                 use crate::movement::my_pathfinding::path;
                 let player_position = {
@@ -172,15 +266,16 @@ async fn player_input (
                     (player_position.x,player_position.y)
                 };
 
-                let mov_map = stepping_tiles(world);
-
-                if let Some(p) = path(mov_map,player_position,mouse_pos) {
+                if let Some(p) = path(world,resources,player_position,mouse_pos,resources.settings.diagonals) {
                     if p.0.len() > 1 {
                         let diff = (p.0[1].0-p.0[0].0,p.0[1].1-p.0[0].1);
-                        if diff.0 == -1 {actions.push(PlayerAction::TryWalk(Dir::W))}
-                        if diff.0 ==  1 {actions.push(PlayerAction::TryWalk(Dir::E))}
-                        if diff.1 == -1 {actions.push(PlayerAction::TryWalk(Dir::N))}
-                        if diff.1 ==  1 {actions.push(PlayerAction::TryWalk(Dir::S))}
+                        if let Some(dir) = Dir::from_components(diff) {
+                            actions.push(PlayerAction::TryWalk(dir));
+                        }
+                    }
+                    //keep walking the rest of the way on later turns
+                    if p.0.len() > 2 {
+                        travel.replace(Travel::To(mouse_pos));
                     }
                     if p.0.len() == 1 {
                         actions.push(PlayerAction::Wait);
@@ -191,7 +286,7 @@ async fn player_input (
     }
     
     if is_mouse_button_pressed(MouseButton::Right) {
-        if let Some(mouse_pos) = mouse_to_map() {
+        if let Some(mouse_pos) = mouse_to_map(world,resources) {
 
             if mouse_pos.0 == player_position.x && mouse_pos.1 == player_position.y {
                 for (ent,(pos,_)) in world.query::<(&Pos,&Item)>().iter(){
@@ -200,6 +295,8 @@ async fn player_input (
                         actions.push(PlayerAction::PickUpItem(ent));
                     }
                 }
+            } else {
+                look_state(world,resources,mouse_pos).await;
             }
 
 
@@ -210,7 +307,7 @@ async fn player_input (
 }
 
 fn highlight_mouse_movement(world: &mut World,resources: &mut Resources) {
-    if let Some(mouse_pos) = mouse_to_map() {
+    if let Some(mouse_pos) = mouse_to_map(world,resources) {
         if resources.fov_set.contains(&mouse_pos) {
             use crate::movement::my_pathfinding::path;
             let player = player(world).unwrap();
@@ -219,9 +316,7 @@ fn highlight_mouse_movement(world: &mut World,resources: &mut Resources) {
                 (player_position.x,player_position.y)
             };
 
-            let mov_map = stepping_tiles(world);
-
-            if let Some(p) = path(mov_map,player_position,mouse_pos) {
+            if let Some(p) = path(world,resources,player_position,mouse_pos,resources.settings.diagonals) {
                 
                 
                 if p.0.len() > 1 {
@@ -229,209 +324,19 @@ fn highlight_mouse_movement(world: &mut World,resources: &mut Resources) {
                         match i {
                             0 => {}
                             1 => {
-                                let index = (tile.0+(tile.1*ARENA_WIDTH as i32)) as usize;
-                                if index < resources.highlights.len() {
-                                    resources.highlights[index] = Some(WHITE);
-                                }
+                                resources.highlights.insert(*tile,WHITE);
                             }
                             _ => {
-                                //resources.highlights[(tile.0+(tile.1*ARENA_WIDTH as i32)) as usize] = Some(LIGHTGRAY);
+                                //resources.highlights.insert(*tile,LIGHTGRAY);
                             }
                         }
                     }
                 }
                 if p.0.len() == 1 {
-                    resources.highlights[(player_position.0 + (player_position.1*ARENA_WIDTH as i32)) as usize] = Some(WHITE);
+                    resources.highlights.insert(player_position,WHITE);
                 }
             }
         }
     }
 }
 
-async fn player_actions(world: &mut World, resources: &mut Resources, actions: Vec<PlayerAction>) -> bool {
-    let mut action_taken = false;
-    let player = player(world).unwrap();
-    let player_position = {
-        let player_position = world.get::<Pos>(player).unwrap();
-        Pos::new(player_position.x,player_position.y)
-    };
-    for action in actions {
-        match action {
-            PlayerAction::Wait => {
-                action_taken = true;
-            }
-            PlayerAction::TryWalk(dir) => {
-                let mut bumped: Option<(Entity,Bump)> = None;
-                let map = movement_map(world);
-                for (_,(_,mut pos)) in world.query::<(&Player,&mut Pos)>().iter() {
-                    let (dx,dy) = dir.components();
-                    let destination = (pos.x + dx,pos.y + dy);
-                    if let Some((ent,bump)) = map.get(&destination) {
-                        bumped.replace((*ent,*bump));
-                    } else {
-                        pos.x = destination.0;
-                        pos.y = destination.1;
-                        action_taken = true;
-                    }
-                }
-
-                if let Some((ent,bump)) = bumped {
-                    match bump {
-                        Bump::OpenDoor => {
-                            world.remove_one::<Bump>(ent).ok();
-                            world.remove_one::<BlocksSight>(ent).ok();
-                            if let Ok(mut appearance) = world.get_mut::<Appearance>(ent) {
-                                appearance.sprite = 7;
-                            }
-                            action_taken = true;
-                        }
-                        Bump::Attack => {
-                            let mut attacks_list: Vec<(Entity,AttackData)> = Vec::new();
-                            if let Some(attack_data) = get_attack(world,player) {
-                                if attack_data.axe {
-                                    let adjacent_tiles: Vec<(i32,i32)> = 
-                                    [(-1,-1),(0,-1,),(1,-1),
-                                     (-1,0),         (1,0),
-                                     (-1,1), (0,1),  (1,1)].iter().map(|diff|{
-                                         (player_position.x+diff.0,
-                                         player_position.y+diff.1)
-                                    }).collect();
-
-                                    for tile in adjacent_tiles {
-                                        if let Some((ent,bump)) = map.get(&tile) {
-                                            match bump {
-                                                Bump::Attack => {
-                                                    attacks_list.push((*ent,attack_data.clone()));
-                                                },
-                                                _ => {}
-                                            }
-                                        }
-                                    }
-
-                                } else {
-                                    attacks_list.push((ent,attack_data));
-                                }
-                                action_taken = true;
-                            }
-                            for (ent,attack_data) in attacks_list {
-                                attack(world,player,ent,attack_data);
-                            }
-                        }
-                        Bump::NextLevel => {
-                            resources.new_level_request = true;
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            PlayerAction::PickUpItem(ent) => {
-                world.remove_one::<Pos>(ent).ok();
-                world.remove_one::<OnLevel>(ent).ok();
-                resources.player.inventory.push(ent);
-                action_taken = true;
-            }
-            PlayerAction::DropItem(ent) => {
-                world.insert_one(ent,player_position.clone()).ok();
-                world.insert_one(ent,OnLevel).ok();
-                let mut items_to_remove = Vec::new();
-                for (index,item) in resources.player.inventory.iter().enumerate() {
-                    if *item == ent {
-                        items_to_remove.push(index);
-                    }
-                }
-                for index in items_to_remove {
-                    resources.player.inventory.remove(index);
-                }
-                action_taken = true;
-            }
-            PlayerAction::EquipItem(ent) => {
-                let mut equipable = None;
-                if let Ok(item) = world.get::<Equipable>(ent) {
-                    equipable.replace((*item).clone());
-                }
-                if let Some(equipable) = equipable {
-                    if let Ok(mut player_equipment) = world.get_mut::<Equipment>(player) {
-                        match equipable {
-                            Equipable::Armor => {
-                                if let Some(currently_equiped) = player_equipment.armor {
-                                    resources.player.inventory.push(currently_equiped);
-                                }
-                                player_equipment.armor.replace(ent);
-                            },
-                            Equipable::Weapon => {
-                                if let Some(currently_equiped) = player_equipment.weapon {
-                                    resources.player.inventory.push(currently_equiped);
-                                }
-                                player_equipment.weapon.replace(ent);
-
-                            }
-                        }
-                        let mut index_to_remove = Vec::new();
-                        for (i,e) in resources.player.inventory.iter().enumerate() {
-                            if *e == ent {
-                                index_to_remove.push(i);
-                            }
-                        }
-                        for i in index_to_remove {
-                            resources.player.inventory.remove(i);
-                        }
-                    }
-                }
-                action_taken = true;
-            }
-            PlayerAction::UseItem(ent) => {
-                let useable: Option<Useable> = get_cloned(world,ent);
-                if let Some(useable) = useable {
-                    let mut item_used = false;
-                    match useable {
-                        Useable::Heal => {
-                            if let Ok(mut health) = world.get_mut::<Health>(player) {
-                                health.current = health.max;
-                                action_taken = true;
-                            }
-                            item_used = true;
-                        }
-                        Useable::MagicMapping => {
-                            update_fov(world,resources,true);
-                            update_fov(world,resources,false);
-                            item_used = true;
-                        }
-                        Useable::Throw(attack_data,mulch) => {
-                            let mut targets = Vec::new();
-                            if let Some(tile) = aiming_state(world,resources,&attack_data).await {
-                                item_used = true;
-                                for target_ent in get_entities_at(world,tile) {
-                                    if let Ok(_) = world.get::<Health>(target_ent) {
-                                        targets.push(target_ent);
-                                    }
-                                }
-                                if !mulch {
-                                    world.insert_one(ent,Pos::new(tile.0,tile.1)).ok();
-                                }
-                            }
-
-                            for target in targets {
-                                attack(world,player,target,attack_data.clone());
-                            }
-
-                            
-                        }
-                    }
-                    if item_used {
-                        let mut index_to_remove = Vec::new();
-                        for (i,e) in resources.player.inventory.iter().enumerate() {
-                            if *e == ent {
-                                index_to_remove.push(i);
-                            }
-                        }
-                        for i in index_to_remove {
-                            resources.player.inventory.remove(i);
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    action_taken
-}
@@ -18,16 +18,23 @@ pub async fn control_screen() {
                 break;
             }
 
-        let tile_size = screen_height()/(ARENA_HEIGHT as f32+2.);
+        let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
 
         for (i,msg) in vec![
             "Press C at any time to view controls",
-            "WASD or left mouse click to move",
+            "WASD, arrow keys or left mouse click to move",
+            "YUBN or the numpad to move diagonally, M in the menu turns diagonals off",
             "Space to wait",
+            "O to explore until something turns up, clicking far away walks there",
             "E or right click self to pickup items",
             "Tab or I to open inventory",
-            "Items can be equiped or used from the inventory",
+            "In the inventory D, E or Q then a letter drops, equips or uses an item",
+            "or pick one with the arrow keys and press Enter",
             "Walk into enemies to attack them",
+            "F to fire a bow, arrows can sometimes be picked back up",
+            "L, X or right click a tile to look at it",
+            "Page Up/Down or mouse wheel to scroll the log",
+            "Escape to save and return to the menu",
         ].into_iter().enumerate() {
             draw_text_ex(
                 msg,
@@ -5,7 +5,7 @@ use crate::prelude::*;
 
 pub async fn game_over(world: &mut World,resources: &mut Resources) -> StateChange {
     loop {
-        let tile_size = screen_height()/(ARENA_HEIGHT as f32+2.);
+        let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
         
         draw_text_ex(
             format!("You died on floor {}",resources.level).as_str(),
@@ -18,6 +18,17 @@ pub async fn game_over(world: &mut World,resources: &mut Resources) -> StateChan
             }
         );
 
+        draw_text_ex(
+            format!("Seed: {}",resources.seed).as_str(),
+            tile_size*3.,tile_size*12.,
+            TextParams {
+                font_size: (tile_size*0.8) as u16,
+                color: GRAY,
+                font: resources.font,
+                ..Default::default()
+            }
+        );
+
         next_frame().await;
         if is_key_pressed(KeyCode::Space) ||
             is_key_pressed(KeyCode::Enter) ||
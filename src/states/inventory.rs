@@ -1,7 +1,9 @@
 use macroquad::prelude::*;
 use hecs::*;
 use crate::prelude::*;
-use crate::states::game::PlayerAction;
+use crate::simulation::PlayerAction;
+use crate::combat::{get_attack,get_defense,AttackData};
+use crate::states::look::describe_attack;
 use macroquad::ui::{
     hash, root_ui,
     widgets::{self,Group},
@@ -19,6 +21,7 @@ pub async fn inventory_state(
     //next_frame to refresh any input buffers
     draw(world,resources);
     next_frame().await;
+    while get_char_pressed().is_some() {};
 
     //load state
     let items: Vec<(Entity,Option<Name>,Option<Equipable>,Option<Useable>)> = {
@@ -26,7 +29,13 @@ pub async fn inventory_state(
         for &item in resources.player.inventory.iter() {
             let mut entry = (item,None,None,None);
             if let Ok(name) = world.get::<Name>(item) {
-                entry.1.replace((*name).clone());
+                let mut name = (*name).clone();
+                if let Ok(stack) = world.get::<Stack>(item) {
+                    if stack.count > 1 {
+                        name.name = format!("{} ({})",name.name,stack.count);
+                    }
+                }
+                entry.1.replace(name);
             }
             if let Ok(equipable) = world.get::<Equipable>(item) {
                 entry.2.replace((*equipable).clone());
@@ -40,19 +49,74 @@ pub async fn inventory_state(
     };
 
     let equipment: Equipment = get_cloned(world,player).unwrap();
-    let mut weapon_data: Option<(Entity,String)> = None;
-    if let Some(weapon_id) = equipment.weapon {
-        if let Some(name) = get_cloned::<Name>(world,weapon_id) {
-            weapon_data.replace((weapon_id,name.name));
-        }
-    }
+    //each slot with what is in it and its stats, an empty weapon slot
+    //shows the player's bare hands
+    let slots: Vec<(Equipable,Option<Entity>,String,String)> = vec![
+        (
+            Equipable::Weapon,
+            equipment.weapon,
+            format!("Weapon: {}",slot_name(world,equipment.weapon,"Bare hands")),
+            get_attack(world,player).map(|attack|describe_attack(&attack)).unwrap_or_default(),
+        ),
+        (
+            Equipable::Armor,
+            equipment.armor,
+            format!("Armor: {}",slot_name(world,equipment.armor,"None")),
+            equipment.armor.and_then(|ent|item_stats(world,ent)).unwrap_or_default(),
+        ),
+    ];
+    let defense = get_defense(world,player);
+    let comparisons: Vec<Option<String>> = items.iter()
+        .map(|(ent,_,_,_)|compare_to_equipped(world,player,*ent))
+        .collect();
+    let stacked: Vec<bool> = items.iter()
+        .map(|(ent,_,_,_)|get_cloned::<Stack>(world,*ent).map(|stack|stack.count > 1).unwrap_or(false))
+        .collect();
 
     //the vec of any actions taken
     let mut actions: Vec<PlayerAction> = Vec::new();
+    let mut selection: usize = 0;
+    let mut verb: Option<Verb> = None;
+    let mut prompt = "Inventory".to_owned();
     loop {
         //input
-        if is_key_pressed(macroquad::input::KeyCode::Escape) {
-            break;
+        if is_key_pressed(KeyCode::Escape) {
+            if verb.take().is_none() {
+                break;
+            }
+            prompt = "Inventory".to_owned();
+        }
+        if !items.is_empty() {
+            if is_key_pressed(KeyCode::Down) || is_key_pressed(KeyCode::Kp2) {
+                selection = (selection+1).min(items.len()-1);
+            }
+            if is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::Kp8) {
+                selection = selection.saturating_sub(1);
+            }
+        }
+        //enter acts on the selected item, with the verb typed first or
+        //whatever the item is for if there wasn't one
+        let mut chosen = None;
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+            chosen = Some(selection);
+        }
+        while let Some(c) = get_char_pressed() {
+            match (verb,Verb::from_key(c),item_index(c)) {
+                (None,Some(new_verb),_) => {
+                    verb = Some(new_verb);
+                    prompt = new_verb.prompt().to_owned();
+                }
+                (Some(_),_,Some(index)) => chosen = Some(index),
+                (None,None,Some(index)) if index < items.len() => selection = index,
+                _ => {}
+            }
+        }
+        if let Some((ent,_,equipable,useable)) = chosen.and_then(|index|items.get(index)) {
+            let verb = verb.take().unwrap_or(if useable.is_some() {Verb::Use} else {Verb::Equip});
+            match verb.action(*ent,equipable.is_some(),useable.is_some()) {
+                Ok(action) => actions.push(action),
+                Err(msg) => prompt = msg.to_owned(),
+            }
         }
         draw(world,resources);
         
@@ -60,15 +124,24 @@ pub async fn inventory_state(
             .titlebar(false)
             .movable(false)
             .ui(&mut *root_ui(), |ui| {
-                Group::new(hash!("Equipment label"),Vec2::new(600.,100.)).ui(ui,|ui|{
+                Group::new(hash!("Equipment label"),Vec2::new(600.,70.)).ui(ui,|ui|{
                     ui.label(Vec2::new(10.,10.),&format!("{}","Equipment".to_owned()));
-                    if let Some((ent,name)) = &weapon_data {
-                        ui.label(Vec2::new(10.,40.),&format!("Weapon: {}",name));
-                    }
+                    ui.label(Vec2::new(10.,40.),&format!("Dodging {} Armor {}",defense.dodging,defense.armor));
                 });
+                for (i,(slot,ent,name,stats)) in slots.iter().enumerate() {
+                    Group::new(hash!("equipment",i), Vec2::new(600.,60.)).ui(ui, |ui| {
+                        ui.label(Vec2::new(10.,5.),name);
+                        ui.label(Vec2::new(10.,30.),stats);
+                        if ent.is_some() && ui.button(vec2(450.,10.),"Unequip") {
+                            actions.push(PlayerAction::Unequip(*slot));
+                        }
+                    });
+                }
+
 
-                Group::new(hash!("inventory label"), Vec2::new(600.,40.)).ui(ui, |ui| {
-                    ui.label(Vec2::new(10.,10.),&format!("{}","Inventory".to_owned()));
+                Group::new(hash!("inventory label"), Vec2::new(600.,65.)).ui(ui, |ui| {
+                    ui.label(Vec2::new(10.,10.),&prompt);
+                    ui.label(Vec2::new(10.,35.),"d drop, D drop one, e equip, q use, then a letter or Enter for the selected item");
                 });
                 for (i,(ent,name,equipable,useable)) in items.iter().enumerate() {
                     let name = {
@@ -79,8 +152,16 @@ pub async fn inventory_state(
                         }
                     };
 
+                    let cursor = if i == selection {">"} else {" "};
+                    let letter = item_letter(i).map(|c|format!("{})",c)).unwrap_or_default();
                     Group::new(hash!("inventory",i), Vec2::new(600.,60.)).ui(ui, |ui| {
-                        ui.label(Vec2::new(10.,10.),&format!("{}",name));
+                        ui.label(Vec2::new(10.,5.),&format!("{} {} {}",cursor,letter,name));
+                        if let Some(comparison) = &comparisons[i] {
+                            ui.label(Vec2::new(10.,30.),comparison);
+                        }
+                        if stacked[i] && ui.button(vec2(330.,10.),"Drop 1") {
+                            actions.push(PlayerAction::DropOne(*ent));
+                        }
                         if ui.button(vec2(400.,10.),"Drop") {
                             actions.push(PlayerAction::DropItem(*ent));
                         }
@@ -91,7 +172,7 @@ pub async fn inventory_state(
                         }
                         if useable.is_some() {
                             if ui.button(vec2(500.,10.),"Use") {
-                                actions.push(PlayerAction::UseItem(*ent));
+                                actions.push(PlayerAction::UseItem(*ent,None));
                             }
                         }
                     });
@@ -109,3 +190,96 @@ pub async fn inventory_state(
     next_frame().await;
     actions
 }
+
+#[derive(Copy,Clone)]
+enum Verb {
+    Drop,
+    DropOne,
+    Equip,
+    Use,
+}
+
+impl Verb {
+    fn from_key(c: char) -> Option<Self> {
+        match c {
+            'd' => Some(Verb::Drop),
+            'D' => Some(Verb::DropOne),
+            'e' => Some(Verb::Equip),
+            'q' => Some(Verb::Use),
+            _ => None,
+        }
+    }
+
+    fn prompt(&self) -> &'static str {
+        match self {
+            Verb::Drop => "Drop which item?",
+            Verb::DropOne => "Drop one of which item?",
+            Verb::Equip => "Equip which item?",
+            Verb::Use => "Use which item?",
+        }
+    }
+
+    fn action(&self, ent: Entity, equipable: bool, useable: bool) -> Result<PlayerAction,&'static str> {
+        match self {
+            Verb::Drop => Ok(PlayerAction::DropItem(ent)),
+            Verb::DropOne => Ok(PlayerAction::DropOne(ent)),
+            Verb::Equip if equipable => Ok(PlayerAction::EquipItem(ent)),
+            Verb::Use if useable => Ok(PlayerAction::UseItem(ent,None)),
+            Verb::Equip => Err("You can't equip that"),
+            Verb::Use => Err("You can't use that"),
+        }
+    }
+}
+
+//items are lettered a to z in inventory order
+fn item_letter(index: usize) -> Option<char> {
+    if index < 26 {
+        Some((b'a'+index as u8) as char)
+    } else {
+        None
+    }
+}
+
+fn item_index(c: char) -> Option<usize> {
+    if c.is_ascii_lowercase() {
+        Some((c as u8-b'a') as usize)
+    } else {
+        None
+    }
+}
+
+fn slot_name(world: &World, slot: Option<Entity>, empty: &str) -> String {
+    slot.and_then(|ent|get_cloned::<Name>(world,ent))
+        .map(|name|name.name)
+        .unwrap_or_else(||empty.to_owned())
+}
+
+fn item_stats(world: &World, item: Entity) -> Option<String> {
+    if let Some(armor) = get_cloned::<Armor>(world,item) {
+        return Some(format!("Dodging {:+} Armor {:+}",armor.defense.dodging,armor.defense.armor));
+    }
+    get_cloned::<Weapon>(world,item).map(|weapon|describe_attack(&weapon.attack))
+}
+
+//an equipable item's stats and how they differ from what it would replace
+fn compare_to_equipped(world: &World, player: Entity, item: Entity) -> Option<String> {
+    let stats = item_stats(world,item)?;
+    let equipment = get_cloned::<Equipment>(world,player)?;
+    let difference = match get_cloned::<Equipable>(world,item)? {
+        Equipable::Weapon => {
+            let new = get_cloned::<Weapon>(world,item)?.attack;
+            let old = get_attack(world,player)?;
+            let average = |attack: &AttackData| (attack.damage_low+attack.damage_high) as f32/2.;
+            format!("damage {:+.1} to hit {:+}",average(&new)-average(&old),new.to_hit-old.to_hit)
+        }
+        Equipable::Armor => {
+            let new = get_cloned::<Armor>(world,item)?.defense;
+            let old = equipment.armor
+                .and_then(|ent|get_cloned::<Armor>(world,ent))
+                .map(|armor|armor.defense)
+                .unwrap_or_default();
+            format!("dodging {:+} armor {:+}",new.dodging-old.dodging,new.armor-old.armor)
+        }
+    };
+    Some(format!("{} ({})",stats,difference))
+}
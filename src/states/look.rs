@@ -0,0 +1,202 @@
+use hecs::*;
+use macroquad::prelude::*;
+use crate::prelude::*;
+use crate::combat::*;
+use crate::hud::{wrap_text,LOG_HEIGHT};
+
+//moves a cursor over the map and describes whatever is under it,
+//nothing in here takes a turn
+pub async fn look_state(world: &World, resources: &Resources, start: (i32,i32)) {
+    next_frame().await;
+    let mut cursor = start;
+    let mut last_mouse = mouse_position();
+    loop {
+        if is_key_pressed(KeyCode::Escape) ||
+            is_key_pressed(KeyCode::L) ||
+            is_key_pressed(KeyCode::X) ||
+            is_mouse_button_pressed(MouseButton::Right) {
+                break;
+            }
+
+        //the cursor stays on the part of the floor that's on screen
+        let camera = camera(world,resources);
+        let (width,height) = resources.spatial_index.size();
+        let mut step = |dx: i32, dy: i32| {
+            cursor.0 = (cursor.0+dx).clamp(camera.0,camera.0+VIEW_WIDTH.min(width) as i32-1);
+            cursor.1 = (cursor.1+dy).clamp(camera.1,camera.1+VIEW_HEIGHT.min(height) as i32-1);
+        };
+        if is_key_pressed(KeyCode::W) || is_key_pressed(KeyCode::Up) {step(0,-1);}
+        if is_key_pressed(KeyCode::A) || is_key_pressed(KeyCode::Left) {step(-1,0);}
+        if is_key_pressed(KeyCode::S) || is_key_pressed(KeyCode::Down) {step(0,1);}
+        if is_key_pressed(KeyCode::D) || is_key_pressed(KeyCode::Right) {step(1,0);}
+
+        //the mouse only takes over the cursor once it moves
+        if mouse_position() != last_mouse {
+            last_mouse = mouse_position();
+            if let Some(tile) = mouse_to_map(world,resources) {
+                cursor = tile;
+            }
+        }
+
+        draw_map_and_hud(world,resources);
+
+        let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
+        let (x,y) = map_to_screen(camera,cursor,tile_size);
+        draw_rectangle_lines(x,y,tile_size,tile_size,3.,YELLOW);
+
+        draw_look_panel(resources,&look_at(world,resources,cursor),tile_size);
+
+        next_frame().await;
+    }
+    next_frame().await;
+}
+
+//lines describing a tile, visible tiles show everything on them while
+//remembered ones only show what the player saw there
+pub fn look_at(world: &World, resources: &Resources, tile: (i32,i32)) -> Vec<(String,Color)> {
+    let visible = resources.fov_set.contains(&tile);
+
+    let mut entities: Vec<(Entity,u32)> = get_entities_at(resources,tile).into_iter()
+        .filter(|ent|world.get::<PlayerMemory>(*ent).is_ok() != visible)
+        .filter(|ent|world.get::<Name>(*ent).is_ok())
+        .map(|ent|{
+            let layer = get_cloned::<Appearance>(world,ent).map(|a|a.layer).unwrap_or(0);
+            (ent,layer)
+        })
+        .collect();
+    entities.sort_by_key(|(_,layer)|std::cmp::Reverse(*layer));
+
+    let mut lines = Vec::new();
+    if entities.is_empty() {
+        lines.push(("You can't see anything there".to_owned(),GRAY));
+        return lines;
+    }
+
+    for (ent,_) in entities {
+        let name = get_cloned::<Name>(world,ent).unwrap();
+        let color = get_cloned::<Appearance>(world,ent).map(|a|a.color).unwrap_or(WHITE);
+        if visible {
+            lines.push((name.name,color));
+        } else {
+            lines.push((format!("{} (remembered)",name.name),color));
+        }
+        if !name.description.is_empty() {
+            lines.push((name.description,LIGHTGRAY));
+        }
+        if !visible {
+            continue;
+        }
+
+        if let Some(stack) = get_cloned::<Stack>(world,ent) {
+            if stack.count > 1 {
+                lines.push((format!("{} of them",stack.count),LIGHTGRAY));
+            }
+        }
+        if let Some(health) = get_cloned::<Health>(world,ent) {
+            lines.push((format!("Health {}/{}",health.current,health.max),LIGHTGRAY));
+        }
+        if let Some(energy) = get_cloned::<Energy>(world,ent) {
+            if energy.speed > NORMAL_SPEED {
+                lines.push(("Moves quickly".to_owned(),LIGHTGRAY));
+            } else if energy.speed < NORMAL_SPEED {
+                lines.push(("Moves slowly".to_owned(),LIGHTGRAY));
+            }
+        }
+        if let Some(effects) = get_cloned::<StatusEffects>(world,ent) {
+            for effect in effects.effects.iter() {
+                lines.push((format!("{} for {} turns",capitalize(effect.kind.adjective()),effect.turns),effect.kind.icon().1));
+            }
+        }
+        if world.get::<Defense>(ent).is_ok() || world.get::<Equipment>(ent).is_ok() {
+            let defense = get_defense(world,ent);
+            lines.push((format!("Dodging {} Armor {}",defense.dodging,defense.armor),LIGHTGRAY));
+        }
+        if let Some(armor) = get_cloned::<Armor>(world,ent) {
+            lines.push((format!("Worn: Dodging {:+} Armor {:+}",armor.defense.dodging,armor.defense.armor),LIGHTGRAY));
+        }
+        let attack = if world.get::<Health>(ent).is_ok() {
+            get_attack(world,ent)
+        } else if let Some(Useable::Throw(attack,_)) | Some(Useable::Fireball(attack)) = get_cloned::<Useable>(world,ent) {
+            Some(attack)
+        } else {
+            get_cloned::<Weapon>(world,ent).map(|w|w.attack)
+        };
+        if let Some(attack) = attack {
+            lines.push((describe_attack(&attack),LIGHTGRAY));
+        }
+        lines.push((String::new(),LIGHTGRAY));
+    }
+    lines
+}
+
+pub fn describe_attack(attack: &AttackData) -> String {
+    let range = match attack.range {
+        Range::Meele => "melee".to_owned(),
+        Range::Ranged(range) => format!("range {}",range),
+    };
+    let mut text = format!("Damage {}-{} To hit {:+} {}",
+        attack.damage_low,attack.damage_high,attack.to_hit,range);
+    if attack.axe {
+        text.push_str(", hits all around");
+    }
+    if attack.fireball {
+        text.push_str(&format!(", explodes in radius {}",attack.radius));
+    }
+    if attack.cost > ACTION_COST {
+        text.push_str(", slow");
+    } else if attack.cost < ACTION_COST {
+        text.push_str(", quick");
+    }
+    text
+}
+
+fn draw_look_panel(resources: &Resources, lines: &[(String,Color)], tile_size: f32) {
+    let x = tile_size*(VIEW_WIDTH as f32+2.);
+    let w = screen_width()-x;
+    let h = screen_height()-tile_size*(LOG_HEIGHT+1.);
+    let font_size = (tile_size*0.6) as u16;
+    let line_height = tile_size*0.7;
+
+    draw_rectangle(x,0.,w,h,BLACK);
+    draw_text_ex(
+        "Look",
+        x+tile_size,tile_size*0.8,
+        TextParams {
+            font_size: (tile_size*0.8) as u16,
+            color: WHITE,
+            font: resources.font,
+            ..Default::default()
+        });
+
+    let mut y = tile_size*1.8;
+    for (line,color) in lines {
+        for row in wrap_text(line,w-tile_size*2.,resources.font,font_size) {
+            if y > h-line_height {
+                break;
+            }
+            draw_text_ex(
+                row.as_str(),
+                x+tile_size,y,
+                TextParams {
+                    font_size,
+                    color: *color,
+                    font: resources.font,
+                    ..Default::default()
+                });
+            y += line_height;
+        }
+        if line.is_empty() {
+            y += line_height*0.5;
+        }
+    }
+
+    draw_text_ex(
+        "Esc or right click to stop looking",
+        x+tile_size,h-tile_size*0.4,
+        TextParams {
+            font_size: (tile_size*0.5) as u16,
+            color: GRAY,
+            font: resources.font,
+            ..Default::default()
+        });
+}
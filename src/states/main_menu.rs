@@ -4,12 +4,22 @@ use macroquad::prelude::*;
 use crate::prelude::*;
 use crate::resources::*;
 use crate::states::game_controls::*;
+use crate::save::*;
+use crate::simulation::new_game;
+use crate::replay::replay_exists;
+use crate::settings::save_settings;
 
 pub async fn main_menu(world: &mut World, resources: &mut Resources) -> StateChange {
     world.clear();
     *resources = load_resources().await;
+    let can_continue = save_exists();
+    let can_replay = replay_exists();
+    let mut seed_input = String::new();
+    //anything that went wrong saving or loading, shown under the options
+    let mut error: Option<String> = None;
+    while get_char_pressed().is_some() {};
     loop {
-        let tile_size = screen_height()/(ARENA_HEIGHT as f32+2.);
+        let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
          draw_text_ex(
             "Welcome to...",
             tile_size*2.,tile_size*3.,
@@ -62,7 +72,108 @@ pub async fn main_menu(world: &mut World, resources: &mut Resources) -> StateCha
                 ..Default::default()
             }
         );
+
+        if can_continue {
+            draw_text_ex(
+                "Press L to continue your last run",
+                tile_size*2.,tile_size*16.,
+                TextParams {
+                    font_size: tile_size as u16,
+                    color: LIGHTGRAY,
+                    font: resources.font,
+                    ..Default::default()
+                }
+            );
+        }
+
+        if can_replay {
+            draw_text_ex(
+                "Press R to watch the last run",
+                tile_size*2.,tile_size*18.,
+                TextParams {
+                    font_size: tile_size as u16,
+                    color: LIGHTGRAY,
+                    font: resources.font,
+                    ..Default::default()
+                }
+            );
+        }
+
+        let movement_text = if resources.settings.diagonals {
+            "Press M for 4-way movement, now 8-way"
+        } else {
+            "Press M for 8-way movement, now 4-way"
+        };
+        draw_text_ex(
+            movement_text,
+            tile_size*2.,tile_size*20.,
+            TextParams {
+                font_size: tile_size as u16,
+                color: GRAY,
+                font: resources.font,
+                ..Default::default()
+            }
+        );
+
+        let seed_text = if seed_input.is_empty() {
+            "Type a number to choose a seed".to_owned()
+        } else {
+            format!("Seed: {}",seed_input)
+        };
+        draw_text_ex(
+            seed_text.as_str(),
+            tile_size*2.,tile_size*19.,
+            TextParams {
+                font_size: tile_size as u16,
+                color: GRAY,
+                font: resources.font,
+                ..Default::default()
+            }
+        );
+        if let Some(error) = &error {
+            draw_text_ex(
+                error.as_str(),
+                tile_size*2.,tile_size*22.,
+                TextParams {
+                    font_size: (tile_size*0.8) as u16,
+                    color: RED,
+                    font: resources.font,
+                    ..Default::default()
+                }
+            );
+        }
         next_frame().await;
+
+        while let Some(c) = get_char_pressed() {
+            if c.is_ascii_digit() && seed_input.len() < 19 {
+                seed_input.push(c);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            seed_input.pop();
+        }
+
+        if is_key_pressed(KeyCode::M) {
+            resources.settings.diagonals = !resources.settings.diagonals;
+            if let Err(e) = save_settings(&resources.settings) {
+                error = Some(e);
+            }
+        }
+
+        if can_replay && is_key_pressed(KeyCode::R) {
+            return StateChange::Replace(GameState::Replay);
+        }
+
+        if can_continue && is_key_pressed(KeyCode::L) {
+            match load_game(world,resources) {
+                Ok(()) => {
+                    return StateChange::Replace(GameState::Game);
+                }
+                Err(e) => {
+                    error = Some(e);
+                }
+            }
+        }
         if is_key_pressed(KeyCode::Space) ||
             is_key_pressed(KeyCode::Enter) ||
             is_key_pressed(KeyCode::Escape) {
@@ -74,15 +185,9 @@ pub async fn main_menu(world: &mut World, resources: &mut Resources) -> StateCha
         }
 
     }
-    macroquad::rand::srand((get_time()*100000.) as u64);
-    start_with_health_pot(world,resources);
+    let seed = seed_input.parse()
+        .unwrap_or((get_time()*100000.) as u64);
+    new_game(world,resources,seed);
     StateChange::Replace(GameState::Game)
 }
 
-
-fn start_with_health_pot(world:&mut World,resources:&mut Resources) {
-    let ent = spawn(world,EntityKind::HealthPotion);
-    world.remove_one::<OnLevel>(ent).ok();
-    resources.player.inventory.push(ent);
-}
-
@@ -8,12 +8,15 @@ pub mod main_menu;
 pub mod aiming;
 pub mod game_controls;
 pub mod win;
+pub mod replay;
+pub mod look;
 
 pub enum GameState {
     Game,
     GameOver,
     MainMenu,
     Win,
+    Replay,
 }
 
 pub enum StateChange {
@@ -28,6 +31,7 @@ impl GameState {
             GameState::GameOver => {game_over::game_over(world,resources).await}
             GameState::MainMenu => {main_menu::main_menu(world,resources).await}
             GameState::Win => {win::win_state(world,resources).await}
+            GameState::Replay => {replay::replay_state(world,resources).await}
         }
     }
 }
@@ -0,0 +1,123 @@
+use hecs::*;
+use macroquad::prelude::*;
+use crate::prelude::*;
+use crate::replay::*;
+use crate::simulation::*;
+
+const SPEEDS: [f64;5] = [1.,2.,4.,8.,16.];
+
+pub async fn replay_state(world: &mut World, resources: &mut Resources) -> StateChange {
+    let replay = match load_replay(REPLAY_PATH) {
+        Ok(replay) => replay,
+        Err(e) => {
+            replay_error(resources,&e).await;
+            return StateChange::Replace(GameState::MainMenu);
+        }
+    };
+    start_replay(world,resources,&replay);
+
+    let mut turn = 0;
+    let mut paused = false;
+    let mut speed = 1;
+    let mut last_step = get_time();
+    next_frame().await;
+    loop {
+        if is_key_pressed(KeyCode::Escape) {
+            break;
+        }
+        if is_key_pressed(KeyCode::Space) {
+            paused = !paused;
+        }
+        if is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::Equal) {
+            speed = (speed+1).min(SPEEDS.len()-1);
+        }
+        if is_key_pressed(KeyCode::Down) || is_key_pressed(KeyCode::Minus) {
+            speed = speed.saturating_sub(1);
+        }
+
+        scroll_message_log(resources);
+
+        let finished = turn >= replay.turns.len() ||
+            !matches!(run_status(world,resources),RunStatus::Playing);
+
+        let step = if paused {
+            is_key_pressed(KeyCode::Period) || is_key_pressed(KeyCode::Right)
+        } else {
+            get_time() - last_step >= 1./SPEEDS[speed]
+        };
+
+        if step && !finished {
+            play_turn(world,resources,&replay,turn);
+            turn += 1;
+            last_step = get_time();
+        }
+
+        update_fov(world,resources,false);
+        draw_map_and_hud(world,resources);
+        animation_system(resources);
+        draw_animations(world,resources);
+
+        let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
+        let status = if finished {
+            "finished".to_owned()
+        } else if paused {
+            "paused".to_owned()
+        } else {
+            format!("x{}",SPEEDS[speed])
+        };
+        draw_text_ex(
+            format!("Replay turn {}/{} ({})",turn,replay.turns.len(),status).as_str(),
+            tile_size,tile_size*0.8,
+            TextParams {
+                font_size: (tile_size*0.8) as u16,
+                color: WHITE,
+                font: resources.font,
+                ..Default::default()
+            }
+        );
+        draw_text_ex(
+            "Space pause  . step  +/- speed  Esc quit",
+            tile_size,screen_height()-tile_size*0.2,
+            TextParams {
+                font_size: (tile_size*0.6) as u16,
+                color: LIGHTGRAY,
+                font: resources.font,
+                ..Default::default()
+            }
+        );
+        next_frame().await;
+    }
+    StateChange::Replace(GameState::MainMenu)
+}
+
+//shows why the replay couldn't be loaded until a key is pressed
+async fn replay_error(resources: &Resources, error: &str) {
+    next_frame().await;
+    loop {
+        let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
+        draw_text_ex(
+            "The last run couldn't be replayed",
+            tile_size*2.,tile_size*10.,
+            TextParams {
+                font_size: tile_size as u16,
+                color: LIGHTGRAY,
+                font: resources.font,
+                ..Default::default()
+            }
+        );
+        draw_text_ex(
+            error,
+            tile_size*2.,tile_size*12.,
+            TextParams {
+                font_size: (tile_size*0.8) as u16,
+                color: RED,
+                font: resources.font,
+                ..Default::default()
+            }
+        );
+        next_frame().await;
+        if get_last_key_pressed().is_some() {
+            break;
+        }
+    }
+}
@@ -4,7 +4,7 @@ use crate::prelude::*;
 
 pub async fn win_state(world: &mut World,resources: &mut Resources) -> StateChange {
     loop {
-        let tile_size = screen_height()/(ARENA_HEIGHT as f32+2.);
+        let tile_size = screen_height()/(VIEW_HEIGHT as f32+2.);
         
         draw_text_ex(
             "Congratulations! You escaped!",
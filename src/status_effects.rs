@@ -0,0 +1,144 @@
+use hecs::*;
+use macroquad::color::*;
+use crate::prelude::*;
+use crate::combat::remove_dead_entities;
+
+//how long the speed and strength potions last
+pub const POTION_TURNS: u32 = 20;
+pub const STRENGTH_BONUS: i32 = 2;
+pub const HASTE_DODGING: i32 = 3;
+pub const BLIND_TO_HIT: i32 = -5;
+
+impl StatusKind {
+    //reads as "you are poisoned"
+    pub fn adjective(&self) -> &'static str {
+        match self {
+            StatusKind::Poison => "poisoned",
+            StatusKind::Bleeding => "bleeding",
+            StatusKind::Haste => "hasted",
+            StatusKind::Slow => "slowed",
+            StatusKind::Confusion => "confused",
+            StatusKind::Blindness => "blinded",
+            StatusKind::Regeneration => "regenerating",
+            StatusKind::Strength => "strengthened",
+        }
+    }
+
+    //what the hud draws for the effect
+    pub fn icon(&self) -> (&'static str,Color) {
+        match self {
+            StatusKind::Poison => ("P",GREEN),
+            StatusKind::Bleeding => ("B",RED),
+            StatusKind::Haste => ("H",SKYBLUE),
+            StatusKind::Slow => ("S",BROWN),
+            StatusKind::Confusion => ("C",PINK),
+            StatusKind::Blindness => ("X",DARKGRAY),
+            StatusKind::Regeneration => ("R",LIME),
+            StatusKind::Strength => ("+",ORANGE),
+        }
+    }
+}
+
+impl StatusEffects {
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|effect|effect.kind == kind)
+    }
+}
+
+pub fn has_status(world: &World, ent: Entity, kind: StatusKind) -> bool {
+    world.get::<StatusEffects>(ent)
+        .map(|effects|effects.has(kind))
+        .unwrap_or(false)
+}
+
+//adds an effect, or extends it if the entity already has it
+pub fn apply_status(world: &mut World, resources: &mut Resources, ent: Entity, effect: StatusEffect) {
+    if world.get::<StatusEffects>(ent).is_err() {
+        world.insert_one(ent,StatusEffects::default()).ok();
+    }
+    if let Ok(mut effects) = world.get_mut::<StatusEffects>(ent) {
+        if let Some(existing) = effects.effects.iter_mut().find(|e|e.kind == effect.kind) {
+            existing.turns = existing.turns.max(effect.turns);
+        } else {
+            effects.effects.push(effect);
+        }
+    }
+    let color = if world.get::<Player>(ent).is_ok() {YELLOW} else {LIGHTGRAY};
+    log(resources,format!("{} {} {}",capitalize(&the_name(world,ent)),to_be(world,ent),effect.kind.adjective()),color);
+}
+
+//energy gained per tick, after haste, slow and carrying too much
+pub fn effective_speed(energy: &Energy, effects: Option<&StatusEffects>, burdened: bool) -> i32 {
+    let mut speed = energy.speed;
+    if burdened {
+        speed /= 2;
+    }
+    if let Some(effects) = effects {
+        if effects.has(StatusKind::Haste) {
+            speed *= 2;
+        }
+        if effects.has(StatusKind::Slow) {
+            speed /= 2;
+        }
+    }
+    speed.max(1)
+}
+
+//runs once a turn, deals damage over time and counts every effect down
+pub fn status_system(world: &mut World, resources: &mut Resources) {
+    let mut damage: Vec<(Entity,i32)> = Vec::new();
+    let mut expired: Vec<(Entity,StatusKind)> = Vec::new();
+    let time = resources.time/TICKS_PER_TURN;
+    for (ent,(effects,health)) in world.query::<(&mut StatusEffects,Option<&mut Health>)>().iter() {
+        for effect in effects.effects.iter() {
+            match effect.kind {
+                StatusKind::Poison if time.is_multiple_of(2) => damage.push((ent,1)),
+                StatusKind::Bleeding => damage.push((ent,1)),
+                _ => {}
+            }
+        }
+        if let Some(health) = health {
+            if effects.has(StatusKind::Regeneration) {
+                health.current = (health.current+1).min(health.max);
+            }
+        }
+        for effect in effects.effects.iter_mut() {
+            effect.turns = effect.turns.saturating_sub(1);
+            if effect.turns == 0 {
+                expired.push((ent,effect.kind));
+            }
+        }
+        effects.effects.retain(|effect|effect.turns > 0);
+    }
+
+    for (ent,amount) in damage {
+        let mut died = false;
+        if let Ok(mut health) = world.get_mut::<Health>(ent) {
+            health.current -= amount;
+            died = health.current <= 0;
+        }
+        if died && world.get::<Player>(ent).is_err() {
+            log(resources,format!("{} dies",capitalize(&the_name(world,ent))),GREEN);
+        }
+    }
+
+    for (ent,kind) in expired {
+        if world.get::<Player>(ent).is_ok() {
+            log(resources,format!("You are no longer {}",kind.adjective()),LIGHTGRAY);
+        }
+    }
+
+    remove_dead_entities(world,resources);
+}
+
+fn the_name(world: &World, ent: Entity) -> String {
+    if world.get::<Player>(ent).is_ok() {
+        log_name(world,ent)
+    } else {
+        format!("the {}",log_name(world,ent))
+    }
+}
+
+fn to_be(world: &World, ent: Entity) -> &'static str {
+    if world.get::<Player>(ent).is_ok() {"are"} else {"is"}
+}
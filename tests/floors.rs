@@ -0,0 +1,38 @@
+use ended::dungeon_gen::*;
+use ended::lists::dungeon_templates;
+
+const SEEDS: u64 = 1000;
+
+#[test]
+fn every_floor_can_be_finished() {
+    ended::resources::load_data_from_dir("assets").unwrap();
+    for seed in 0..SEEDS {
+        for level in 1..=10 {
+            let map = build_floor(level,level_seed(seed,level));
+            let unreachable = map.unreachable();
+            assert!(unreachable.is_empty(),"seed {} floor {} can't reach {:?}",seed,level,unreachable);
+        }
+    }
+}
+
+#[test]
+fn templates_connect_in_every_orientation() {
+    ended::resources::load_data_from_dir("assets").unwrap();
+    for (i,template) in dungeon_templates().into_iter().enumerate() {
+        for transposed in [false,true] {
+            let mut template = template.clone();
+            if transposed {
+                template.transpose();
+            }
+            for turns in 0..4 {
+                let unreachable = template.unreachable_rooms();
+                let cut_off: Vec<&usize> = std::iter::once(&template.exit)
+                    .chain(template.treasure_rooms.iter())
+                    .filter(|room|unreachable.contains(room))
+                    .collect();
+                assert!(cut_off.is_empty(),"template {} transposed {} turned {} times cuts off rooms {:?}",i,transposed,turns,cut_off);
+                template.rotate();
+            }
+        }
+    }
+}
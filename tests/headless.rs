@@ -0,0 +1,51 @@
+use hecs::*;
+use ended::prelude::*;
+use ended::simulation::*;
+use ended::replay::*;
+
+fn state(world: &World, resources: &Resources) -> Vec<String> {
+    let mut state: Vec<String> = world.query::<(&Pos,&Name,Option<&Health>)>().iter()
+        .map(|(_,(pos,name,health))|format!("{} {},{} {:?}",name.name,pos.x,pos.y,health.map(|h|h.current)))
+        .collect();
+    state.sort();
+    state.push(format!("level {} time {}",resources.level,resources.time));
+    state
+}
+
+//plays a seeded walk around the tower without a window
+fn play(seed: u64) -> (World,Resources) {
+    let mut world = World::new();
+    let mut resources = Resources::headless();
+    new_game(&mut world,&mut resources,seed);
+    let dirs = [Dir::N,Dir::S,Dir::E,Dir::W,Dir::NE,Dir::NW,Dir::SE,Dir::SW];
+    let mut lcg = seed;
+    for _ in 0..300 {
+        if !matches!(run_status(&world,&resources),RunStatus::Playing) {
+            break;
+        }
+        lcg = lcg.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let action = match (lcg>>33)%10 {
+            0 => PlayerAction::Wait,
+            roll => PlayerAction::TryWalk(dirs[roll as usize%dirs.len()]),
+        };
+        take_turn(&mut world,&mut resources,vec![action]);
+    }
+    (world,resources)
+}
+
+#[test]
+fn same_seed_same_run() {
+    ended::resources::load_data_from_dir("assets").unwrap();
+    for seed in 0..5 {
+        let (world,resources) = play(seed);
+        let (again_world,again_resources) = play(seed);
+        assert!(resources.time > 0);
+        assert_eq!(state(&world,&resources),state(&again_world,&again_resources),"seed {}",seed);
+
+        let replay = resources.replay.clone().unwrap();
+        let mut replay_world = World::new();
+        let mut replay_resources = Resources::headless();
+        run_replay(&mut replay_world,&mut replay_resources,&replay);
+        assert_eq!(state(&world,&resources),state(&replay_world,&replay_resources),"seed {}",seed);
+    }
+}
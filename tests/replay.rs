@@ -0,0 +1,77 @@
+use hecs::*;
+use ended::prelude::*;
+use ended::simulation::*;
+use ended::replay::*;
+use ended::save::*;
+
+//everything about the run that playing it back has to get right
+fn state(world: &World, resources: &Resources) -> Vec<String> {
+    let mut state: Vec<String> = world.query::<(&Pos,&Name,Option<&Health>)>().iter()
+        .map(|(_,(pos,name,health))|format!("{} {},{} {:?}",name.name,pos.x,pos.y,health.map(|h|h.current)))
+        .collect();
+    state.sort();
+    state.extend(resources.player.inventory.iter()
+        .map(|ent|format!("carried {:?} {:?}",get_cloned::<Name>(world,*ent).map(|n|n.name),get_cloned::<Stack>(world,*ent).map(|s|s.count))));
+    state.push(format!("level {} time {}",resources.level,resources.time));
+    state
+}
+
+//picks the player's next move from a seeded lcg, walking most of the time
+//and otherwise doing something with the items around
+fn next_actions(world: &World, resources: &Resources, lcg: &mut u64) -> Vec<PlayerAction> {
+    *lcg = lcg.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    let roll = (*lcg>>33) as usize;
+    let player = player(world).unwrap();
+    let tile: (i32,i32) = get_cloned::<Pos>(world,player).unwrap().into();
+    let inventory = &resources.player.inventory;
+    let carried = if inventory.is_empty() {None} else {Some(inventory[roll/10%inventory.len()])};
+    match roll%10 {
+        0 => get_entities_at(resources,tile).into_iter()
+            .filter(|ent|world.get::<Item>(*ent).is_ok())
+            .map(PlayerAction::PickUpItem)
+            .collect(),
+        1 => carried.map(PlayerAction::DropOne).into_iter().collect(),
+        2 => carried.filter(|ent|world.get::<Equipable>(*ent).is_ok())
+            .map(PlayerAction::EquipItem).into_iter().collect(),
+        3 => carried.filter(|ent|matches!(get_cloned::<Useable>(world,*ent),Some(Useable::Heal|Useable::Speed|Useable::Strength|Useable::Regeneration)))
+            .map(|ent|PlayerAction::UseItem(ent,None)).into_iter().collect(),
+        _ => vec![PlayerAction::TryWalk([Dir::N,Dir::S,Dir::E,Dir::W][roll/10%4])],
+    }
+}
+
+fn play(world: &mut World, resources: &mut Resources, lcg: &mut u64, turns: usize) {
+    for _ in 0..turns {
+        if !matches!(run_status(world,resources),RunStatus::Playing) {
+            return;
+        }
+        let actions = next_actions(world,resources,lcg);
+        take_turn(world,resources,actions);
+    }
+}
+
+//a run continued from its save twice still plays back to the same place
+#[test]
+fn continued_runs_replay() {
+    ended::resources::load_data_from_dir("assets").unwrap();
+    for seed in 0..5 {
+        let mut world = World::new();
+        let mut resources = Resources::headless();
+        new_game(&mut world,&mut resources,seed);
+        let mut lcg = seed;
+        for _ in 0..2 {
+            play(&mut world,&mut resources,&mut lcg,150);
+            let text = save_to_string(&world,&resources).unwrap();
+            world.clear();
+            resources = Resources::headless();
+            load_from_str(&mut world,&mut resources,&text).unwrap();
+            resume_rng(&mut resources);
+        }
+        play(&mut world,&mut resources,&mut lcg,150);
+
+        let replay = resources.replay.clone().unwrap();
+        let mut replay_world = World::new();
+        let mut replay_resources = Resources::headless();
+        run_replay(&mut replay_world,&mut replay_resources,&replay);
+        assert_eq!(state(&world,&resources),state(&replay_world,&replay_resources),"seed {}",seed);
+    }
+}
@@ -0,0 +1,73 @@
+use hecs::*;
+use ended::prelude::*;
+use ended::simulation::*;
+use ended::save::*;
+
+fn carry(world: &mut World, resources: &mut Resources, id: &str) -> Entity {
+    let ent = spawn_template(world,id);
+    world.remove_one::<OnLevel>(ent).ok();
+    resources.player.inventory.push(ent);
+    ent
+}
+
+fn name(world: &World, ent: Option<Entity>) -> Option<String> {
+    ent.and_then(|ent|get_cloned::<Name>(world,ent)).map(|name|name.name)
+}
+
+//what the player has and is, with entities written out by name so two
+//worlds can be compared
+fn player_state(world: &World, resources: &Resources) -> Vec<String> {
+    let player = player(world).unwrap();
+    let equipment = get_cloned::<Equipment>(world,player).unwrap();
+    let mut state = vec![
+        format!("weapon {:?}",name(world,equipment.weapon)),
+        format!("armor {:?}",name(world,equipment.armor)),
+        format!("effects {:?}",get_cloned::<StatusEffects>(world,player)),
+        format!("energy {:?}",get_cloned::<Energy>(world,player)),
+        format!("pos {:?}",get_cloned::<Pos>(world,player)),
+        format!("level {} time {}",resources.level,resources.time),
+    ];
+    state.extend(resources.player.inventory.iter().map(|ent|{
+        format!("carried {:?} {:?}",name(world,Some(*ent)),get_cloned::<Stack>(world,*ent).map(|stack|stack.count))
+    }));
+    state
+}
+
+#[test]
+fn save_and_load() {
+    ended::resources::load_data_from_dir("assets").unwrap();
+    let mut world = World::new();
+    let mut resources = Resources::headless();
+    new_game(&mut world,&mut resources,7);
+    new_level(&mut world,&mut resources,7);
+
+    let sword = carry(&mut world,&mut resources,"Sword");
+    let armor = carry(&mut world,&mut resources,"Armor");
+    carry(&mut world,&mut resources,"Bow");
+    let potions = carry(&mut world,&mut resources,"SpeedPotion");
+    world.get_mut::<Stack>(potions).unwrap().count = 3;
+    take_turn(&mut world,&mut resources,vec![
+        PlayerAction::EquipItem(sword),
+        PlayerAction::EquipItem(armor),
+        PlayerAction::UseItem(potions,None),
+    ]);
+    let before = player_state(&world,&resources);
+    assert!(before.contains(&"weapon Some(\"Sword\")".to_owned()));
+    assert!(before.iter().any(|line|line.starts_with("effects Some") && line.contains("Haste")));
+    assert_eq!(resources.level,2);
+
+    let text = save_to_string(&world,&resources).unwrap();
+    let mut loaded_world = World::new();
+    let mut loaded_resources = Resources::headless();
+    load_from_str(&mut loaded_world,&mut loaded_resources,&text).unwrap();
+    assert_eq!(before,player_state(&loaded_world,&loaded_resources));
+
+    //the restored ids have to point at the restored items, not just items
+    //with the same names
+    let player = player(&loaded_world).unwrap();
+    let equipment = get_cloned::<Equipment>(&loaded_world,player).unwrap();
+    for ent in [equipment.weapon.unwrap(),equipment.armor.unwrap()].iter().chain(loaded_resources.player.inventory.iter()) {
+        assert!(loaded_world.contains(*ent));
+        assert!(loaded_world.get::<Item>(*ent).is_ok());
+    }
+}